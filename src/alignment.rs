@@ -0,0 +1,219 @@
+/// the status of a character once the typed text is aligned with the source text
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum AlignmentStatus {
+    /// the typed character is the same as the source one
+    Correct,
+    /// the typed character replace the source one
+    Substituted,
+    /// the source character was skipped
+    Missing,
+    /// the typed character doesn't correspond to any source character
+    Extra,
+    /// the source character is after the aligned part of the text, and wasn't typed yet
+    Untyped,
+}
+
+/// a step of an alignment. source_index and typed_index are index (in char) of the transformed
+/// source text and of the typed text.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct AlignedChar {
+    pub status: AlignmentStatus,
+    pub source_index: Option<usize>,
+    pub typed_index: Option<usize>,
+}
+
+/// a part of a line of the source text where every character has the same status. status is
+/// None for the characters that aren't typed, like section headings. Extra typed characters are
+/// in their own runs.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DiffRun {
+    pub status: Option<AlignmentStatus>,
+    pub text: String,
+}
+
+/// Align the typed text with the source text, using the levenshtein distance.
+///
+/// If finished is false, the typed text is aligned with the best matching prefix of the source,
+/// and the rest of the source is returned as Untyped. Otherwise, the whole source is aligned,
+/// and the source character that weren't typed are Missing.
+///
+/// Every source character and every typed character appear exactly once in the result, in order.
+pub fn align(source: &[char], typed: &[char], finished: bool) -> Vec<AlignedChar> {
+    align_with(source, typed, finished, false)
+}
+
+/// Align the end of the typed text with the whole source text, like align with finished being
+/// true. The typed characters before the part that match the beginning of the source are
+/// skipped, and don't appear in the result. It is meant to align a window of the source text,
+/// ending at the current position, without aligning the whole typed text.
+pub fn align_window(source: &[char], typed: &[char]) -> Vec<AlignedChar> {
+    align_with(source, typed, true, true)
+}
+
+fn align_with(
+    source: &[char],
+    typed: &[char],
+    finished: bool,
+    skip_typed_prefix: bool,
+) -> Vec<AlignedChar> {
+    // When the typed prefix is skipped, skipping a typed character cost 1 and every edit cost
+    // more than skipping the whole typed text, so that among the alignments with the fewest
+    // edits, the one that skip the fewest typed characters is chosen.
+    let edit = if skip_typed_prefix {
+        typed.len() as u32 + 1
+    } else {
+        1
+    };
+    let width = source.len() + 1;
+    let mut matrix = vec![0u32; width * (typed.len() + 1)];
+    for (x, cell) in matrix.iter_mut().take(width).enumerate() {
+        *cell = x as u32 * edit;
+    }
+    for y in 1..typed.len() + 1 {
+        matrix[y * width] = y as u32;
+        for x in 1..width {
+            let substitution = matrix[(y - 1) * width + x - 1]
+                + if source[x - 1] == typed[y - 1] {
+                    0
+                } else {
+                    edit
+                };
+            let extra = matrix[(y - 1) * width + x] + edit;
+            let missing = matrix[y * width + x - 1] + edit;
+            matrix[y * width + x] = substitution.min(extra).min(missing);
+        }
+    }
+
+    let last_row = typed.len() * width;
+    let end = if finished {
+        source.len()
+    } else {
+        let mut best = 0;
+        for x in 1..width {
+            if matrix[last_row + x] < matrix[last_row + best] {
+                best = x;
+            }
+        }
+        best
+    };
+
+    let mut result: Vec<AlignedChar> = (end..source.len())
+        .rev()
+        .map(|x| AlignedChar {
+            status: AlignmentStatus::Untyped,
+            source_index: Some(x),
+            typed_index: None,
+        })
+        .collect();
+    let (mut x, mut y) = (end, typed.len());
+    while x > 0 || (y > 0 && !skip_typed_prefix) {
+        let current = matrix[y * width + x];
+        // prefer missing characters over the other operations, so that untyped characters at the
+        // end of the text are reported as missing
+        if x > 0 && current == matrix[y * width + x - 1] + edit {
+            result.push(AlignedChar {
+                status: AlignmentStatus::Missing,
+                source_index: Some(x - 1),
+                typed_index: None,
+            });
+            x -= 1;
+            continue;
+        };
+        if x > 0 && y > 0 {
+            let same = source[x - 1] == typed[y - 1];
+            let diagonal = matrix[(y - 1) * width + x - 1];
+            if current == diagonal + if same { 0 } else { edit } {
+                result.push(AlignedChar {
+                    status: if same {
+                        AlignmentStatus::Correct
+                    } else {
+                        AlignmentStatus::Substituted
+                    },
+                    source_index: Some(x - 1),
+                    typed_index: Some(y - 1),
+                });
+                x -= 1;
+                y -= 1;
+                continue;
+            };
+        };
+        result.push(AlignedChar {
+            status: AlignmentStatus::Extra,
+            source_index: None,
+            typed_index: Some(y - 1),
+        });
+        y -= 1;
+    }
+    result.reverse();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{align, align_window, AlignmentStatus};
+
+    fn statuses(source: &str, typed: &str, finished: bool) -> Vec<AlignmentStatus> {
+        let source: Vec<char> = source.chars().collect();
+        let typed: Vec<char> = typed.chars().collect();
+        align(&source, &typed, finished)
+            .iter()
+            .map(|aligned| aligned.status)
+            .collect()
+    }
+
+    #[test]
+    fn test_align() {
+        use AlignmentStatus::*;
+        assert_eq!(
+            statuses("hello", "hel", false),
+            vec![Correct, Correct, Correct, Untyped, Untyped]
+        );
+        assert_eq!(
+            statuses("hello", "hel", true),
+            vec![Correct, Correct, Correct, Missing, Missing]
+        );
+        assert_eq!(
+            statuses("hello", "hallo", false),
+            vec![Correct, Substituted, Correct, Correct, Correct]
+        );
+        assert_eq!(
+            statuses("hello", "hzello", false),
+            vec![Correct, Extra, Correct, Correct, Correct, Correct]
+        );
+        assert_eq!(
+            statuses("hello world", "helo w", false),
+            vec![
+                Correct, Correct, Correct, Missing, Correct, Correct, Correct, Untyped, Untyped,
+                Untyped, Untyped
+            ]
+        );
+        assert_eq!(statuses("hi", "", false), vec![Untyped, Untyped]);
+    }
+
+    #[test]
+    fn test_align_window() {
+        use AlignmentStatus::*;
+        let window_statuses = |source: &str, typed: &str| {
+            let source: Vec<char> = source.chars().collect();
+            let typed: Vec<char> = typed.chars().collect();
+            align_window(&source, &typed)
+        };
+        let aligned = window_statuses("world", "hello wrold");
+        assert_eq!(
+            aligned
+                .iter()
+                .map(|aligned| aligned.status)
+                .collect::<Vec<_>>(),
+            vec![Correct, Extra, Correct, Missing, Correct, Correct]
+        );
+        assert_eq!(aligned[0].typed_index, Some(6));
+        let aligned = window_statuses("world", "hello wzorld");
+        assert_eq!(
+            aligned
+                .iter()
+                .map(|aligned| aligned.status)
+                .collect::<Vec<_>>(),
+            vec![Correct, Extra, Correct, Correct, Correct, Correct]
+        );
+    }
+}
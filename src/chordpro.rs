@@ -0,0 +1,348 @@
+use crate::{LyricLine, Lyrics, PlayingMusic, Section};
+use log::warn;
+
+/// a chord placed above a lyric line. position is the index (in char) of the lyric character
+/// the chord is placed on.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ChordAnnotation {
+    pub position: usize,
+    pub chord: String,
+}
+
+/// a line of lyrics, with the chords that were inlined in it removed
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ChordProLine {
+    pub lyrics: String,
+    pub chords: Vec<ChordAnnotation>,
+}
+
+impl ChordProLine {
+    fn parse(line: &str) -> Self {
+        let mut lyrics = String::new();
+        let mut chords = Vec::new();
+        let mut lyrics_len = 0;
+        let mut chars = line.chars();
+        while let Some(chara) = chars.next() {
+            if chara == '[' {
+                let mut chord = String::new();
+                let mut closed = false;
+                for chord_char in &mut chars {
+                    if chord_char == ']' {
+                        closed = true;
+                        break;
+                    };
+                    chord.push(chord_char);
+                }
+                if closed {
+                    chords.push(ChordAnnotation {
+                        position: lyrics_len,
+                        chord: chord.trim().to_string(),
+                    });
+                } else {
+                    warn!("unclosed chord in the ChordPro line {:?}", line);
+                    lyrics.push('[');
+                    lyrics_len += 1;
+                    lyrics_len += chord.chars().count();
+                    lyrics.push_str(&chord);
+                };
+            } else {
+                lyrics.push(chara);
+                lyrics_len += 1;
+            };
+        }
+        Self { lyrics, chords }
+    }
+
+    /// return a line with every chord placed above the character it apply to, padded with spaces.
+    /// Return an empty string if this line doesn't have any chord.
+    pub fn chords_text(&self) -> String {
+        let mut result = String::new();
+        let mut result_len = 0;
+        for annotation in &self.chords {
+            if result_len != 0 || annotation.position != 0 {
+                while result_len < annotation.position {
+                    result.push(' ');
+                    result_len += 1;
+                }
+                if result_len > 0 && !result.ends_with(' ') {
+                    result.push(' ');
+                    result_len += 1;
+                };
+            };
+            result.push_str(&annotation.chord);
+            result_len += annotation.chord.chars().count();
+        }
+        result
+    }
+}
+
+/// a section of a song, as delimited by the start_of_* and end_of_* directives. first_line and
+/// end_line are index in the lyrics lines (end_line being excluded).
+#[derive(Debug, PartialEq, Clone)]
+pub struct ChordProSection {
+    /// the kind of section, like "chorus", "verse" or "bridge"
+    pub kind: String,
+    /// the optional label given with the start directive (like "Chorus 2")
+    pub label: Option<String>,
+    pub first_line: usize,
+    pub end_line: usize,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct SongMetadata {
+    pub title: Option<String>,
+    pub subtitle: Option<String>,
+    pub artist: Option<String>,
+    /// directive that are not otherwise understood, as (name, value)
+    pub other: Vec<(String, String)>,
+}
+
+/// A song read from the ChordPro format. Chords are removed from the lyrics, and directives are
+/// kept as metadata.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ChordProSong {
+    pub metadata: SongMetadata,
+    pub lines: Vec<ChordProLine>,
+    pub sections: Vec<ChordProSection>,
+}
+
+/// sections whose content are not lyrics, and so shouldn't be typed
+static NON_LYRICS_SECTIONS: [&str; 2] = ["tab", "grid"];
+
+fn section_kind_from_short(short: &str) -> Option<&'static str> {
+    match short {
+        "c" => Some("chorus"),
+        "v" => Some("verse"),
+        "b" => Some("bridge"),
+        "t" => Some("tab"),
+        "g" => Some("grid"),
+        _ => None,
+    }
+}
+
+impl ChordProSong {
+    pub fn parse(source: &str) -> Self {
+        let mut song = Self::default();
+        let mut open_section: Option<(String, Option<String>, usize)> = None;
+        for line in source.lines() {
+            let trimmed = line.trim();
+            if trimmed.starts_with('#') {
+                continue;
+            };
+            if trimmed.starts_with('{') && trimmed.ends_with('}') {
+                let inner = &trimmed[1..trimmed.len() - 1];
+                let (name, value) = match inner.find(':') {
+                    Some(separator) => (&inner[..separator], Some(inner[separator + 1..].trim())),
+                    None => (inner, None),
+                };
+                let name = name.trim().to_lowercase();
+                let value = value.filter(|v| !v.is_empty()).map(|v| v.to_string());
+
+                let (start_kind, end_kind) = if let Some(kind) = name.strip_prefix("start_of_") {
+                    (Some(kind.to_string()), None)
+                } else if let Some(kind) = name.strip_prefix("end_of_") {
+                    (None, Some(kind.to_string()))
+                } else if name.len() == 3 && name.starts_with("so") {
+                    (
+                        section_kind_from_short(&name[2..]).map(|k| k.to_string()),
+                        None,
+                    )
+                } else if name.len() == 3 && name.starts_with("eo") {
+                    (
+                        None,
+                        section_kind_from_short(&name[2..]).map(|k| k.to_string()),
+                    )
+                } else {
+                    (None, None)
+                };
+
+                if let Some(kind) = start_kind {
+                    if let Some(previous) = open_section.take() {
+                        warn!(
+                            "the section {:?} wasn't closed before a new one started",
+                            previous.0
+                        );
+                        song.close_section(previous);
+                    };
+                    open_section = Some((kind, value, song.lines.len()));
+                } else if let Some(kind) = end_kind {
+                    match open_section.take() {
+                        Some(section) => {
+                            if section.0 != kind {
+                                warn!("the section {:?} was closed as {:?}", section.0, kind);
+                            };
+                            song.close_section(section)
+                        }
+                        None => warn!("end of the section {:?} without a start", kind),
+                    }
+                } else {
+                    match (name.as_str(), value) {
+                        ("title", Some(value)) | ("t", Some(value)) => {
+                            song.metadata.title = Some(value)
+                        }
+                        ("subtitle", Some(value)) | ("st", Some(value)) => {
+                            song.metadata.subtitle = Some(value)
+                        }
+                        ("artist", Some(value)) => song.metadata.artist = Some(value),
+                        (_, value) => song
+                            .metadata
+                            .other
+                            .push((name.clone(), value.unwrap_or_default())),
+                    }
+                };
+                continue;
+            };
+
+            if let Some((kind, _, _)) = &open_section {
+                if NON_LYRICS_SECTIONS.contains(&kind.as_str()) {
+                    continue;
+                };
+            };
+            song.lines.push(ChordProLine::parse(line));
+        }
+        if let Some(section) = open_section.take() {
+            warn!("the section {:?} was never closed", section.0);
+            song.close_section(section);
+        };
+        song
+    }
+
+    fn close_section(&mut self, (kind, label, first_line): (String, Option<String>, usize)) {
+        if NON_LYRICS_SECTIONS.contains(&kind.as_str()) {
+            return;
+        };
+        self.sections.push(ChordProSection {
+            kind,
+            label,
+            first_line,
+            end_line: self.lines.len(),
+        });
+    }
+
+    /// return the lyrics, without chord or directive
+    pub fn lyrics(&self) -> String {
+        self.lines
+            .iter()
+            .map(|line| line.lyrics.as_str())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// return true if at least one line contain a chord
+    pub fn has_chords(&self) -> bool {
+        self.lines.iter().any(|line| !line.chords.is_empty())
+    }
+
+    /// return the lyrics, with the sections of this song. The lines are the same as in this
+    /// song, so chords can be displayed above them.
+    pub fn to_lyrics(&self) -> Lyrics {
+        Lyrics {
+            lines: self
+                .lines
+                .iter()
+                .map(|line| LyricLine {
+                    text: line.lyrics.clone(),
+                    typed: true,
+                })
+                .collect(),
+            sections: self
+                .sections
+                .iter()
+                .map(|section| Section {
+                    name: section.label.clone().unwrap_or_else(|| {
+                        let mut kind = section.kind.chars();
+                        kind.next()
+                            .map(|first| first.to_uppercase().chain(kind).collect())
+                            .unwrap_or_default()
+                    }),
+                    heading_line: None,
+                    first_line: section.first_line,
+                    end_line: section.end_line,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn to_playing_music(&self) -> PlayingMusic {
+        PlayingMusic::from_lyrics(self.to_lyrics())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ChordAnnotation, ChordProSection, ChordProSong};
+
+    static SONG: &str = "{title: Hello}
+{artist: Someone}
+# a comment
+{start_of_verse: Verse 1}
+[Am]Hello [C]world
+no chord here
+{end_of_verse}
+
+{soc}
+[G]la la [D7]la[G]
+{eoc}
+{start_of_tab}
+e|---0---|
+{end_of_tab}
+{key: G}";
+
+    #[test]
+    fn test_chordpro_parse() {
+        let song = ChordProSong::parse(SONG);
+        assert_eq!(song.metadata.title.as_deref(), Some("Hello"));
+        assert_eq!(song.metadata.artist.as_deref(), Some("Someone"));
+        assert_eq!(
+            song.metadata.other,
+            vec![("key".to_string(), "G".to_string())]
+        );
+        assert_eq!(song.lyrics(), "Hello world\nno chord here\n\nla la la");
+        assert_eq!(
+            song.lines[0].chords,
+            vec![
+                ChordAnnotation {
+                    position: 0,
+                    chord: "Am".into()
+                },
+                ChordAnnotation {
+                    position: 6,
+                    chord: "C".into()
+                }
+            ]
+        );
+        assert_eq!(song.lines[0].chords_text(), "Am    C");
+        assert_eq!(song.lines[1].chords_text(), "");
+        assert_eq!(song.lines[3].chords_text(), "G     D7 G");
+        assert_eq!(
+            song.sections,
+            vec![
+                ChordProSection {
+                    kind: "verse".into(),
+                    label: Some("Verse 1".into()),
+                    first_line: 0,
+                    end_line: 2,
+                },
+                ChordProSection {
+                    kind: "chorus".into(),
+                    label: None,
+                    first_line: 3,
+                    end_line: 4,
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_chordpro_playing_music() {
+        let song = ChordProSong::parse(SONG);
+        let mut playing_music = song.to_playing_music();
+        assert_eq!(
+            playing_music.lines(),
+            &vec!["Hello world", "no chord here", "", "la la la"]
+        );
+        assert_eq!(playing_music.sections()[1].name, "Chorus");
+        playing_music.add_typed_text("hello world no chord here la la la");
+        assert!((playing_music.correctness() - 1.0).abs() < 0.00000001);
+    }
+}
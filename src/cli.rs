@@ -0,0 +1,488 @@
+use music_typer::{GameMode, GradeReport, LibraryEntry, Replay, Settings, SettingsOverrides};
+use music_typer::{SongLibrary, Theme};
+use serde::Serialize;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const SCORE_USAGE: &str = "usage: music_typer score --lyrics <song file> \
+--typed <file or directory> [--json] [--threshold <correctness from 0 to 1>]";
+
+pub const GAME_USAGE: &str = "usage: music_typer [--song <song file or title>] \
+[--library <directory>] [--mode <normal|practice|blind>] [--replay <replay file>] \
+[--settings <settings file>] [--windowed|--fullscreen] \
+[--start-line <lyric line number from 1>]";
+
+/// the exit codes of the score command
+const EXIT_BELOW_THRESHOLD: i32 = 1;
+pub const EXIT_ERROR: i32 = 2;
+
+/// the arguments of the game, that override the settings file
+#[derive(Debug, PartialEq, Default)]
+struct GameOptions {
+    /// a song file, or the title of a song of the library
+    song: Option<String>,
+    library: Option<PathBuf>,
+    mode: Option<GameMode>,
+    replay: Option<PathBuf>,
+    settings: Option<PathBuf>,
+    fullscreen: Option<bool>,
+    /// the number of the first lyric line to type, from 1. The lyric lines are the lines typed
+    /// in the game: the repeated sections are written out, and the directives, the comments
+    /// and the empty lines of the file aren't counted. --start-lyric-line is an alias of
+    /// --start-line.
+    start_lyric_line: Option<usize>,
+}
+
+/// the arguments of the score command
+#[derive(Debug, PartialEq)]
+struct ScoreOptions {
+    lyrics: PathBuf,
+    /// a typed text, or a directory of typed texts
+    typed: PathBuf,
+    json: bool,
+    /// the minimum correctness every typed text must have
+    threshold: Option<f64>,
+}
+
+/// the value after an option
+fn option_value<'a>(
+    args: &mut impl Iterator<Item = &'a String>,
+    name: &str,
+) -> Result<&'a str, String> {
+    args.next()
+        .map(String::as_str)
+        .ok_or_else(|| format!("missing value after {}", name))
+}
+
+impl ScoreOptions {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut lyrics = None;
+        let mut typed = None;
+        let mut json = false;
+        let mut threshold = None;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--lyrics" => lyrics = Some(PathBuf::from(option_value(&mut args, arg)?)),
+                "--typed" => typed = Some(PathBuf::from(option_value(&mut args, arg)?)),
+                "--json" => json = true,
+                "--threshold" => {
+                    let value = option_value(&mut args, arg)?;
+                    match value.parse::<f64>() {
+                        Ok(value) if (0.0..=1.0).contains(&value) => threshold = Some(value),
+                        _ => {
+                            return Err(format!(
+                                "invalid threshold {:?}, it must be a correctness from 0 to 1",
+                                value
+                            ))
+                        }
+                    };
+                }
+                _ => return Err(format!("unknown argument {:?}", arg)),
+            };
+        }
+        Ok(Self {
+            lyrics: lyrics.ok_or("missing --lyrics")?,
+            typed: typed.ok_or("missing --typed")?,
+            json,
+            threshold,
+        })
+    }
+}
+
+impl GameOptions {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = Self::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--song" => options.song = Some(option_value(&mut args, arg)?.to_string()),
+                "--library" => options.library = Some(PathBuf::from(option_value(&mut args, arg)?)),
+                "--mode" => options.mode = Some(option_value(&mut args, arg)?.parse()?),
+                "--replay" => options.replay = Some(PathBuf::from(option_value(&mut args, arg)?)),
+                "--settings" => {
+                    options.settings = Some(PathBuf::from(option_value(&mut args, arg)?))
+                }
+                "--windowed" => options.fullscreen = Some(false),
+                "--fullscreen" => options.fullscreen = Some(true),
+                "--start-line" | "--start-lyric-line" => {
+                    let value = option_value(&mut args, arg)?;
+                    match value.parse::<usize>() {
+                        Ok(line) if line >= 1 => options.start_lyric_line = Some(line),
+                        _ => {
+                            return Err(format!(
+                                "invalid start lyric line {:?}, it must be a line number from 1",
+                                value
+                            ))
+                        }
+                    };
+                }
+                _ => return Err(format!("unknown argument {:?}", arg)),
+            };
+        }
+        if options.start_lyric_line.is_some() && options.song.is_none() {
+            return Err("--start-line needs a --song".into());
+        };
+        if options.start_lyric_line.is_some() && options.replay.is_some() {
+            return Err("--start-line can't be used with --replay".into());
+        };
+        Ok(options)
+    }
+}
+
+/// what the game is launched with
+// without the gui, the arguments are only checked
+#[cfg_attr(not(feature = "gui"), allow(dead_code))]
+pub struct GameLaunch {
+    /// the settings read from the settings file
+    pub settings: Settings,
+    /// the settings given on the command line, that are used instead of the ones of the file
+    /// but not saved in it
+    pub overrides: SettingsOverrides,
+    /// the file the settings menu write the settings to
+    pub settings_path: Option<PathBuf>,
+    /// the song to start, or the song of the replay
+    pub song: Option<LibraryEntry>,
+    pub replay: Option<Replay>,
+}
+
+fn load_library(directory: Option<&Path>) -> Result<SongLibrary, String> {
+    let directory = match directory.map(Path::to_path_buf) {
+        Some(directory) => directory,
+        None => match SongLibrary::default_directory() {
+            Some(directory) => directory,
+            None => return Ok(SongLibrary::default()),
+        },
+    };
+    SongLibrary::load(&directory)
+        .map_err(|err| format!("can't read the library {:?}: {}", directory, err))
+}
+
+/// the song file, or else the song of the library with that title
+fn find_song(song: &str, library_directory: Option<&Path>) -> Result<LibraryEntry, String> {
+    let path = Path::new(song);
+    if path.is_file() {
+        return LibraryEntry::load(path)
+            .map_err(|err| format!("can't read the song {:?}: {}", path, err));
+    };
+    let library = load_library(library_directory)?;
+    library
+        .entries
+        .into_iter()
+        .find(|entry| entry.title().map(str::to_lowercase) == Some(song.to_lowercase()))
+        .ok_or_else(|| {
+            format!(
+                "unknown song {:?}, it is neither a file nor the title of a song of the library",
+                song
+            )
+        })
+}
+
+/// read the settings file and the songs given in the arguments of the game
+pub fn game_launch(args: &[String]) -> Result<GameLaunch, String> {
+    let options = GameOptions::parse(args)?;
+    // the given settings file must exist, but not the default one
+    let settings_path = options.settings.clone().or_else(Settings::default_path);
+    let settings = match &settings_path {
+        Some(path) if options.settings.is_some() => Settings::load(path),
+        Some(path) => Settings::load_or_default(path),
+        None => Ok(Settings::default()),
+    }
+    .map_err(|err| {
+        format!(
+            "can't read the settings {:?}: {}",
+            settings_path.as_ref().unwrap(),
+            err
+        )
+    })?;
+    // the game fall back to the default theme, but a wrong name is better reported now
+    Theme::find(&settings.theme)
+        .map_err(|err| format!("can't read the theme {:?}: {}", settings.theme, err))?;
+    if let Some(library) = &options.library {
+        if !library.is_dir() {
+            return Err(format!("the library {:?} isn't a directory", library));
+        };
+    };
+    let overrides = SettingsOverrides {
+        mode: options.mode,
+        fullscreen: options.fullscreen,
+        library: options.library,
+    };
+    let library = overrides.apply(&settings).library;
+    let mut song = match &options.song {
+        Some(song) => Some(find_song(song, library.as_deref())?),
+        None => None,
+    };
+    if let (Some(entry), Some(start_lyric_line)) = (song.as_mut(), options.start_lyric_line) {
+        let line_count = entry.lyrics.lines.len();
+        if start_lyric_line > line_count {
+            return Err(format!(
+                "invalid start lyric line {}, the song has {} lyric lines",
+                start_lyric_line, line_count
+            ));
+        };
+        *entry = entry.starting_at_line(start_lyric_line - 1);
+    };
+    let replay = match &options.replay {
+        Some(path) => {
+            let replay = Replay::load(path)
+                .map_err(|err| format!("can't read the replay {:?}: {}", path, err))?;
+            match &song {
+                Some(entry) if entry.song_hash != replay.song_hash => {
+                    return Err(format!(
+                        "the replay {:?} wasn't recorded on the song {:?}",
+                        path,
+                        options.song.unwrap_or_default()
+                    ))
+                }
+                Some(_) => (),
+                None => {
+                    let library = load_library(library.as_deref())?;
+                    song = library
+                        .entries
+                        .into_iter()
+                        .find(|entry| entry.song_hash == replay.song_hash);
+                    if song.is_none() {
+                        return Err(format!(
+                            "the song {:?} of the replay {:?} isn't in the library, give it with \
+                             --song",
+                            replay.song_id, path
+                        ));
+                    };
+                }
+            };
+            Some(replay)
+        }
+        None => None,
+    };
+    Ok(GameLaunch {
+        settings,
+        overrides,
+        settings_path,
+        song,
+        replay,
+    })
+}
+
+/// the typed texts to grade: the given file, or the files of the given directory
+fn typed_paths(path: &Path) -> io::Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    };
+    let mut paths = Vec::new();
+    for dir_entry in fs::read_dir(path)? {
+        let path = dir_entry?.path();
+        if path.is_file() {
+            paths.push(path);
+        };
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// the report of a typed text, as written in JSON
+#[derive(Serialize)]
+struct TypedReport<'a> {
+    path: String,
+    #[serde(flatten)]
+    report: &'a GradeReport,
+    passed: bool,
+}
+
+/// grade typed texts against lyrics, and return the exit code: 0 if every typed text reach the
+/// threshold, 1 if one of them doesn't, 2 if the arguments or the files are wrong
+pub fn score(args: &[String]) -> i32 {
+    let options = match ScoreOptions::parse(args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("music_typer score: {}\n{}", err, SCORE_USAGE);
+            return EXIT_ERROR;
+        }
+    };
+    let entry = match LibraryEntry::load(&options.lyrics) {
+        Ok(entry) => entry,
+        Err(err) => {
+            eprintln!("can't read the lyrics {:?}: {}", options.lyrics, err);
+            return EXIT_ERROR;
+        }
+    };
+    let paths = match typed_paths(&options.typed) {
+        Ok(paths) => paths,
+        Err(err) => {
+            eprintln!("can't read the typed texts {:?}: {}", options.typed, err);
+            return EXIT_ERROR;
+        }
+    };
+    if paths.is_empty() {
+        eprintln!("no typed text found in {:?}", options.typed);
+        return EXIT_ERROR;
+    };
+    let mut reports = Vec::new();
+    for path in paths {
+        match fs::read_to_string(&path) {
+            Ok(typed_text) => {
+                reports.push((path, GradeReport::new(entry.lyrics.clone(), &typed_text)))
+            }
+            Err(err) => {
+                eprintln!("can't read the typed text {:?}: {}", path, err);
+                return EXIT_ERROR;
+            }
+        };
+    }
+    let passed = |report: &GradeReport| {
+        options
+            .threshold
+            .is_none_or(|threshold| report.correctness >= threshold)
+    };
+
+    if options.json {
+        let typed_reports: Vec<TypedReport> = reports
+            .iter()
+            .map(|(path, report)| TypedReport {
+                path: path.display().to_string(),
+                report,
+                passed: passed(report),
+            })
+            .collect();
+        match serde_json::to_string_pretty(&typed_reports) {
+            Ok(json) => println!("{}", json),
+            Err(err) => {
+                eprintln!("can't write the reports: {}", err);
+                return EXIT_ERROR;
+            }
+        };
+    } else {
+        for (path, report) in &reports {
+            let (correct, wrong, missing) = report.word_counts();
+            println!(
+                "{}: {:.1}% correctness, distance {}, position {}/{}, {} correct words, {} wrong, \
+                 {} missing{}",
+                path.display(),
+                report.correctness * 100.0,
+                report.distance,
+                report.position_in_source_text,
+                report.source_char_count,
+                correct,
+                wrong,
+                missing,
+                if passed(report) {
+                    ""
+                } else {
+                    ", below the threshold"
+                }
+            );
+            for line in report.word_diff_text().lines() {
+                println!("    {}", line);
+            }
+        }
+    };
+    if reports.iter().all(|(_, report)| passed(report)) {
+        0
+    } else {
+        EXIT_BELOW_THRESHOLD
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cli::{self, GameOptions, ScoreOptions, EXIT_ERROR};
+    use music_typer::GameMode;
+    use std::path::PathBuf;
+    use std::{env, fs, process};
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_score_options() {
+        assert_eq!(
+            ScoreOptions::parse(&args(&[
+                "--lyrics",
+                "song.txt",
+                "--typed",
+                "attempts",
+                "--threshold",
+                "0.9",
+                "--json"
+            ])),
+            Ok(ScoreOptions {
+                lyrics: PathBuf::from("song.txt"),
+                typed: PathBuf::from("attempts"),
+                json: true,
+                threshold: Some(0.9),
+            })
+        );
+        assert!(ScoreOptions::parse(&args(&["--lyrics", "song.txt"])).is_err());
+        assert!(ScoreOptions::parse(&args(&["--lyrics"])).is_err());
+        assert!(ScoreOptions::parse(&args(&["--threshold", "90"])).is_err());
+        assert!(ScoreOptions::parse(&args(&["--verbose"])).is_err());
+    }
+
+    #[test]
+    fn test_game_options() {
+        assert_eq!(GameOptions::parse(&[]), Ok(GameOptions::default()));
+        assert_eq!(
+            GameOptions::parse(&args(&[
+                "--song",
+                "Hello",
+                "--mode",
+                "blind",
+                "--fullscreen",
+                "--start-line",
+                "12"
+            ])),
+            Ok(GameOptions {
+                song: Some("Hello".into()),
+                mode: Some(GameMode::Blind),
+                fullscreen: Some(true),
+                start_lyric_line: Some(12),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            GameOptions::parse(&args(&["--song", "Hello", "--start-lyric-line", "3"])),
+            Ok(GameOptions {
+                song: Some("Hello".into()),
+                start_lyric_line: Some(3),
+                ..Default::default()
+            })
+        );
+        assert!(GameOptions::parse(&args(&["--mode", "hard"])).is_err());
+        assert!(GameOptions::parse(&args(&["--song", "a.txt", "--start-line", "0"])).is_err());
+        assert!(GameOptions::parse(&args(&["--start-line", "2"])).is_err());
+        assert!(GameOptions::parse(&args(&[
+            "--replay",
+            "run.json",
+            "--song",
+            "a.txt",
+            "--start-line",
+            "2"
+        ]))
+        .is_err());
+    }
+
+    #[test]
+    fn test_score_empty_typed_directory() {
+        let directory = env::temp_dir().join(format!("music_typer_cli_test_{}", process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(directory.join("attempts")).unwrap();
+        let lyrics = directory.join("song.txt");
+        fs::write(&lyrics, "hello world\n").unwrap();
+        let arguments = args(&[
+            "--lyrics",
+            lyrics.to_str().unwrap(),
+            "--typed",
+            directory.join("attempts").to_str().unwrap(),
+        ]);
+        assert_eq!(cli::score(&arguments), EXIT_ERROR);
+        fs::write(
+            directory.join("attempts").join("first.txt"),
+            "hello world\n",
+        )
+        .unwrap();
+        assert_eq!(cli::score(&arguments), 0);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,220 @@
+use crate::SongMetadata;
+
+/// The fonts a text is written with: the main font, and the fallback fonts that are used, in
+/// order, for the characters the main font has no glyph for. The fonts are numbered from 0 for
+/// the main font, then from 1 for the fallback fonts.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct FontStack {
+    pub main: String,
+    pub fallbacks: Vec<String>,
+}
+
+impl FontStack {
+    pub fn new(main: &str, fallbacks: Vec<String>) -> Self {
+        Self {
+            main: main.to_string(),
+            fallbacks,
+        }
+    }
+
+    /// the path of the font with this number, if there is one
+    pub fn path(&self, font: usize) -> Option<&str> {
+        match font {
+            0 => Some(&self.main),
+            _ => self.fallbacks.get(font - 1).map(String::as_str),
+        }
+    }
+
+    /// the paths of every font, the main one first
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.main.as_str()).chain(self.fallbacks.iter().map(String::as_str))
+    }
+
+    /// the stack with the fonts given in the directives of a song: {textfont: <path>} replace the
+    /// main font, and the fonts of {textfont_fallback: <path>} are tried before the other
+    /// fallback fonts, in the order of the directives.
+    pub fn with_song_fonts(&self, metadata: &SongMetadata) -> Self {
+        let mut stack = self.clone();
+        let mut song_fallbacks = Vec::new();
+        for (name, value) in &metadata.other {
+            if value.is_empty() {
+                continue;
+            };
+            match name.as_str() {
+                "textfont" => stack.main = value.clone(),
+                "textfont_fallback" => song_fallbacks.push(value.clone()),
+                _ => (),
+            };
+        }
+        song_fallbacks.extend(stack.fallbacks);
+        stack.fallbacks = song_fallbacks;
+        stack
+    }
+
+    /// the number of the first font that has a glyph for the character, the main font if none
+    /// has. has_glyph tell if the font with the given number has a glyph for a character.
+    fn font_for(&self, chara: char, has_glyph: &impl Fn(usize, char) -> bool) -> usize {
+        (0..=self.fallbacks.len())
+            .find(|font| has_glyph(*font, chara))
+            .unwrap_or(0)
+    }
+
+    /// Split a text into the sections written with the same font, with the number of the font.
+    /// Every letter is written with the first font that has a glyph for it. The spaces, digits
+    /// and punctuation are written with the font of the letters before them if it has a glyph
+    /// for them, so that they don't split a section, and the ones at the start of the text go
+    /// with the first letter.
+    pub fn split(
+        &self,
+        text: &str,
+        has_glyph: impl Fn(usize, char) -> bool,
+    ) -> Vec<(String, usize)> {
+        let mut sections: Vec<(String, usize)> = Vec::new();
+        let mut leading = String::new();
+        for chara in text.chars() {
+            let is_letter = chara.is_alphabetic();
+            match sections.last_mut() {
+                None if !is_letter => leading.push(chara),
+                None => {
+                    leading.push(chara);
+                    let section = std::mem::take(&mut leading);
+                    sections.push((section, self.font_for(chara, &has_glyph)));
+                }
+                Some((section, font))
+                    if !is_letter && (chara.is_whitespace() || has_glyph(*font, chara)) =>
+                {
+                    section.push(chara)
+                }
+                Some((section, font)) if *font == self.font_for(chara, &has_glyph) => {
+                    section.push(chara)
+                }
+                Some(_) => sections.push((chara.to_string(), self.font_for(chara, &has_glyph))),
+            };
+        }
+        if sections.is_empty() && !leading.is_empty() {
+            let font = leading
+                .chars()
+                .find(|chara| !chara.is_whitespace())
+                .map_or(0, |chara| self.font_for(chara, &has_glyph));
+            sections.push((leading, font));
+        };
+        sections
+    }
+
+    /// the numbers of the fonts used to write a text, in increasing order
+    pub fn fonts_used(&self, text: &str, has_glyph: impl Fn(usize, char) -> bool) -> Vec<usize> {
+        let mut fonts: Vec<usize> = self
+            .split(text, has_glyph)
+            .into_iter()
+            .map(|(_, font)| font)
+            .collect();
+        fonts.sort_unstable();
+        fonts.dedup();
+        fonts
+    }
+
+    /// remove the fallback font with this path, for example when it can't be loaded
+    pub fn remove(&mut self, path: &str) {
+        self.fallbacks.retain(|fallback| fallback != path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ChordProSong, FontStack};
+
+    fn stack() -> FontStack {
+        FontStack::new(
+            "latin.ttf",
+            vec![
+                "japanese.otf".to_string(),
+                "korean.otf".to_string(),
+                "arabic.ttf".to_string(),
+            ],
+        )
+    }
+
+    /// the glyphs of the fonts of the stack, by path: the latin font has the ascii and latin-1
+    /// characters, the japanese one has kana, a few kanji, the CJK punctuation and the ascii
+    /// characters, and the others have their script and the spaces
+    fn glyphs(stack: &FontStack) -> impl Fn(usize, char) -> bool + '_ {
+        move |font, chara| {
+            let code = u32::from(chara);
+            match stack.path(font) {
+                Some("latin.ttf") | Some("song.ttf") => code < 0x250,
+                Some("japanese.otf") => {
+                    code < 0x80 || (0x3000..=0x30FF).contains(&code) || "世界歌".contains(chara)
+                }
+                Some("korean.otf") => chara == ' ' || (0xAC00..=0xD7A3).contains(&code),
+                Some("arabic.ttf") => chara == ' ' || (0x600..=0x6FF).contains(&code),
+                Some("chinese.otf") => chara == ' ' || "歌".contains(chara),
+                _ => false,
+            }
+        }
+    }
+
+    #[test]
+    fn test_font_stack_split() {
+        let stack = stack();
+        let has_glyph = glyphs(&stack);
+        assert_eq!(
+            stack.split("Hello 世界と、 안녕 world!", &has_glyph),
+            vec![
+                ("Hello ".to_string(), 0),
+                ("世界と、 ".to_string(), 1),
+                ("안녕 ".to_string(), 2),
+                ("world!".to_string(), 0),
+            ]
+        );
+        // the leading punctuation take the font of the first letter, and a punctuation that the
+        // font of the section doesn't have goes to the first font that has it
+        assert_eq!(
+            stack.split("« مرحبا » - Привет", &has_glyph),
+            vec![("« مرحبا ".to_string(), 3), ("» - Привет".to_string(), 0)]
+        );
+        // the characters that no font has are written with the main font
+        assert_eq!(
+            stack.split("नमस्ते", &has_glyph),
+            vec![("नमस्ते".to_string(), 0)]
+        );
+        assert_eq!(
+            stack.split("1, 2", &has_glyph),
+            vec![("1, 2".to_string(), 0)]
+        );
+        assert_eq!(
+            stack.split("「」", &has_glyph),
+            vec![("「」".to_string(), 1)]
+        );
+        assert!(stack.split("", &has_glyph).is_empty());
+        assert_eq!(
+            stack.fonts_used("un 歌 deux 노래 trois", &has_glyph),
+            vec![0, 1, 2]
+        );
+        assert_eq!(stack.path(2), Some("korean.otf"));
+        assert_eq!(stack.path(4), None);
+    }
+
+    #[test]
+    fn test_font_stack_song_fonts() {
+        let song = ChordProSong::parse(
+            "{title: 歌}\n{textfont: song.ttf}\n{textfont_fallback: chinese.otf}\n歌 and かな",
+        );
+        let stack = stack().with_song_fonts(&song.metadata);
+        assert_eq!(stack.main, "song.ttf");
+        let has_glyph = glyphs(&stack);
+        assert_eq!(
+            stack.split("歌 and かな", &has_glyph),
+            vec![
+                ("歌 ".to_string(), 1),
+                ("and ".to_string(), 0),
+                ("かな".to_string(), 2),
+            ]
+        );
+        assert_eq!(stack.path(1), Some("chinese.otf"));
+        let mut stack = stack.clone();
+        stack.remove("chinese.otf");
+        let has_glyph = glyphs(&stack);
+        assert_eq!(stack.split("歌", &has_glyph), vec![("歌".to_string(), 1)]);
+        assert_eq!(stack.path(1), Some("japanese.otf"));
+    }
+}
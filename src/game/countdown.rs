@@ -0,0 +1,87 @@
+use super::{absolute_text, color, despawn_all, AppState, Fonts, APP_STATE_STAGE};
+use super::{OngoingMusic, OngoingReplay};
+use crate::Theme;
+use bevy::prelude::*;
+
+/// the number of seconds before the song start
+const COUNTDOWN_SECONDS: f32 = 3.0;
+
+pub fn build(app: &mut AppBuilder) {
+    app.add_resource(CountdownTimer(Timer::from_seconds(
+        COUNTDOWN_SECONDS,
+        false,
+    )))
+    .on_state_enter(
+        APP_STATE_STAGE,
+        AppState::Countdown,
+        start_countdown.system(),
+    )
+    .on_state_update(
+        APP_STATE_STAGE,
+        AppState::Countdown,
+        countdown_system.system(),
+    )
+    .on_state_exit(
+        APP_STATE_STAGE,
+        AppState::Countdown,
+        despawn_all::<CountdownText>.system(),
+    );
+}
+
+struct CountdownTimer(Timer);
+
+/// the text that display the remaining seconds
+struct CountdownText;
+
+/// restart the ongoing music, so that it is ready to be played when the countdown finish
+fn start_countdown(
+    commands: &mut Commands,
+    fonts: Res<Fonts>,
+    theme: Res<Theme>,
+    mut timer: ResMut<CountdownTimer>,
+    mut ongoing_music: ResMut<OngoingMusic>,
+    mut ongoing_replay: ResMut<OngoingReplay>,
+) {
+    timer.0.reset();
+    ongoing_replay.0 = None;
+    if let Some(music) = ongoing_music.0.as_mut() {
+        music.restart();
+    };
+    let position = Rect {
+        top: Val::Px(300.0),
+        left: Val::Percent(50.0),
+        ..Default::default()
+    };
+    commands
+        .spawn(absolute_text(
+            format!("{:.0}", COUNTDOWN_SECONDS),
+            position,
+            &fonts,
+            fonts.ongoing_music_font_size * 2.0,
+            color(theme.text),
+        ))
+        .with(CountdownText);
+}
+
+fn countdown_system(
+    time: Res<Time>,
+    keyboard_input: Res<Input<KeyCode>>,
+    ongoing_music: Res<OngoingMusic>,
+    mut timer: ResMut<CountdownTimer>,
+    mut state: ResMut<State<AppState>>,
+    mut query: Query<&mut Text, With<CountdownText>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) || ongoing_music.0.is_none() {
+        state.set_next(AppState::SongSelect).unwrap();
+        return;
+    };
+    timer.0.tick(time.delta_seconds());
+    if timer.0.finished() {
+        state.set_next(AppState::Playing).unwrap();
+        return;
+    };
+    let remaining = (COUNTDOWN_SECONDS - timer.0.elapsed()).ceil();
+    for mut text in query.iter_mut() {
+        text.value = format!("{:.0}", remaining);
+    }
+}
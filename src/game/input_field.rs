@@ -0,0 +1,226 @@
+use super::playing::{OngoingMusicDisplaySetting, PlayingEntity};
+use super::APP_STATE_STAGE;
+use super::{absolute_text, lyrics_text, AppState, Fonts, OngoingMusic, UiMaterials};
+use crate::{InputFieldView, COLUMN_WIDTH_RATIO};
+use bevy::prelude::*;
+
+/// how fast the input field scroll to its new position, the remaining offset being divided by e
+/// every 1 / SCROLL_SPEED seconds
+const SCROLL_SPEED: f32 = 20.0;
+/// the time the caret stay visible, then hidden
+const CARET_BLINK_SECONDS: f32 = 0.5;
+
+pub fn build(app: &mut AppBuilder) {
+    app.add_resource(CaretBlink(Timer::from_seconds(CARET_BLINK_SECONDS, true)))
+        .on_state_update(
+            APP_STATE_STAGE,
+            AppState::Playing,
+            update_input_field_system.system(),
+        )
+        .on_state_update(
+            APP_STATE_STAGE,
+            AppState::Playing,
+            scroll_input_field_system.system(),
+        )
+        .on_state_update(
+            APP_STATE_STAGE,
+            AppState::Playing,
+            blink_caret_system.system(),
+        )
+        .add_system(layout_input_field_system.system());
+}
+
+/// the node that contains the runs of the typed text, its right side being at the middle of the
+/// screen
+struct InputField {
+    /// the runs of text, with their color. None until it is displayed for the first time.
+    runs: Option<Vec<(String, Color)>>,
+    /// the width of the displayed text, in columns
+    width: usize,
+    /// how much the text is still to be moved to the left, in pixel
+    offset: f32,
+}
+
+/// the caret placed after the input field
+struct InputFieldCaret;
+
+/// the caret is toggled every time this timer finish, and shown again when a key is typed
+struct CaretBlink(Timer);
+
+pub fn spawn_input_field(
+    commands: &mut Commands,
+    fonts: &Fonts,
+    ui_materials: &UiMaterials,
+    ongoing_music_setting: &OngoingMusicDisplaySetting,
+) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    right: Val::Percent(50.0),
+                    top: Val::Px(ongoing_music_setting.current_y),
+                    ..Default::default()
+                },
+                flex_direction: FlexDirection::Row,
+                ..Default::default()
+            },
+            material: ui_materials.transparent.clone(),
+            ..Default::default()
+        })
+        .with(InputField {
+            runs: None,
+            width: 0,
+            offset: 0.0,
+        })
+        .with(PlayingEntity);
+    let position = Rect {
+        left: Val::Percent(50.0),
+        top: Val::Px(ongoing_music_setting.current_y),
+        ..Default::default()
+    };
+    commands
+        .spawn(absolute_text(
+            "|".to_string(),
+            position,
+            fonts,
+            fonts.ongoing_music_font_size,
+            ongoing_music_setting.caret_color,
+        ))
+        .with(InputFieldCaret)
+        .with(PlayingEntity);
+}
+
+/// display the end of the typed text, after every keystroke. The mistakes are colored, and the
+/// pending space is shown as an underscore. The text is written with the fonts of the lyrics.
+fn update_input_field_system(
+    commands: &mut Commands,
+    ongoing_music: Res<OngoingMusic>,
+    ongoing_music_setting: Res<OngoingMusicDisplaySetting>,
+    fonts: Res<Fonts>,
+    font_assets: Res<Assets<Font>>,
+    mut caret_blink: ResMut<CaretBlink>,
+    mut last_keystroke_count: Local<Option<usize>>,
+    mut field_query: Query<(Entity, &mut InputField, Option<&Children>)>,
+    mut caret_query: Query<&mut Draw, With<InputFieldCaret>>,
+) {
+    let playing_music = match ongoing_music.0.as_ref() {
+        Some(playing_music) => playing_music,
+        None => return,
+    };
+    let keystroke_count = playing_music.keystrokes().len();
+    let never_displayed = field_query
+        .iter_mut()
+        .any(|(_, field, _)| field.runs.is_none());
+    if *last_keystroke_count == Some(keystroke_count) && !never_displayed {
+        return;
+    };
+    *last_keystroke_count = Some(keystroke_count);
+    let view = InputFieldView::new(playing_music, ongoing_music_setting.input_field_columns);
+    let mut runs: Vec<(String, Color)> = view
+        .runs()
+        .into_iter()
+        .map(|(text, mistake)| {
+            let color = if mistake {
+                ongoing_music_setting.substituted_color
+            } else {
+                ongoing_music_setting.typed_color
+            };
+            (text, color)
+        })
+        .collect();
+    if view.pending_space {
+        runs.push(("_".to_string(), ongoing_music_setting.pending_space_color));
+    };
+    let column_width = fonts.ongoing_music_font_size * COLUMN_WIDTH_RATIO;
+    for (entity, mut field, children) in field_query.iter_mut() {
+        if field.runs.as_ref() == Some(&runs) {
+            continue;
+        };
+        // keep the text where it was, then scroll it to its new place
+        if field.runs.is_some() {
+            field.offset += (view.width() as f32 - field.width as f32) * column_width;
+        };
+        field.width = view.width();
+        if let Some(children) = children {
+            for child in children.iter() {
+                commands.despawn_recursive(*child);
+            }
+        };
+        commands.set_current_entity(entity);
+        commands.with_children(|parent| {
+            for (value, color) in &runs {
+                for (section, font) in fonts.split_lyrics(&font_assets, value) {
+                    parent.spawn(lyrics_text(
+                        section,
+                        font,
+                        &fonts,
+                        fonts.ongoing_music_font_size,
+                        *color,
+                    ));
+                }
+            }
+        });
+        field.runs = Some(runs.clone());
+    }
+    caret_blink.0.reset();
+    for mut draw in caret_query.iter_mut() {
+        draw.is_visible = true;
+    }
+}
+
+/// move the input field and its caret to current_y when the layout change, and display it again
+/// with the new number of columns and colors
+fn layout_input_field_system(
+    ongoing_music_setting: ChangedRes<OngoingMusicDisplaySetting>,
+    mut queries: QuerySet<(
+        Query<(&mut InputField, &mut Style)>,
+        Query<(&mut Style, &mut Text), With<InputFieldCaret>>,
+    )>,
+) {
+    for (mut field, mut style) in queries.q0_mut().iter_mut() {
+        style.position.top = Val::Px(ongoing_music_setting.current_y);
+        field.runs = None;
+        field.offset = 0.0;
+    }
+    for (mut style, mut text) in queries.q1_mut().iter_mut() {
+        style.position.top = Val::Px(ongoing_music_setting.current_y);
+        text.style.color = ongoing_music_setting.caret_color;
+    }
+}
+
+/// move the input field and its caret to their place, independently of the frame rate
+fn scroll_input_field_system(
+    time: Res<Time>,
+    mut queries: QuerySet<(
+        Query<(&mut InputField, &mut Style)>,
+        Query<&mut Style, With<InputFieldCaret>>,
+    )>,
+) {
+    let mut offset = 0.0;
+    for (mut field, mut style) in queries.q0_mut().iter_mut() {
+        field.offset *= (-time.delta_seconds() * SCROLL_SPEED).exp();
+        if field.offset.abs() < 0.5 {
+            field.offset = 0.0;
+        };
+        style.margin.right = Val::Px(-field.offset);
+        offset = field.offset;
+    }
+    for mut style in queries.q1_mut().iter_mut() {
+        style.margin.left = Val::Px(offset);
+    }
+}
+
+fn blink_caret_system(
+    time: Res<Time>,
+    mut caret_blink: ResMut<CaretBlink>,
+    mut query: Query<&mut Draw, With<InputFieldCaret>>,
+) {
+    caret_blink.0.tick(time.delta_seconds());
+    if !caret_blink.0.just_finished() {
+        return;
+    };
+    for mut draw in query.iter_mut() {
+        draw.is_visible = !draw.is_visible;
+    }
+}
@@ -0,0 +1,65 @@
+use super::playing::OngoingMusicDisplaySetting;
+use super::Fonts;
+use crate::{ScreenLayout, Settings};
+use bevy::{prelude::*, window::WindowResized};
+
+pub fn build(app: &mut AppBuilder) {
+    app.add_system(window_layout_system.system());
+}
+
+#[derive(Default)]
+struct WindowLayoutState {
+    resized_reader: EventReader<WindowResized>,
+    /// the scale factor of the window when the layout was last applied
+    scale_factor: f64,
+    /// the layout that was last applied, None until the window is known
+    layout: Option<ScreenLayout>,
+    /// the settings the layout was computed with
+    settings: Option<Settings>,
+}
+
+/// compute the layout from the size of the primary window and the settings when the game start
+/// and every time they change, then resize the texts already displayed
+fn window_layout_system(
+    mut state: Local<WindowLayoutState>,
+    resized_events: Res<Events<WindowResized>>,
+    windows: Res<Windows>,
+    settings: Res<Settings>,
+    mut fonts: ResMut<Fonts>,
+    mut ongoing_music_setting: ResMut<OngoingMusicDisplaySetting>,
+    mut query: Query<&mut Text>,
+) {
+    let resized = state.resized_reader.iter(&resized_events).count() > 0;
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+    // a window moved to a screen with another scale factor isn't always resized
+    let rescaled = window.scale_factor() != state.scale_factor;
+    let settings_changed = state.settings.as_ref() != Some(&settings);
+    if state.layout.is_some() && !resized && !rescaled && !settings_changed {
+        return;
+    };
+    state.scale_factor = window.scale_factor();
+    if settings_changed {
+        state.settings = Some(settings.clone());
+    };
+    let layout = ScreenLayout::from_settings(
+        window.width(),
+        window.height(),
+        window.scale_factor(),
+        &settings,
+    );
+    if state.layout.as_ref() == Some(&layout) {
+        return;
+    };
+    let ratio = layout.font_size / fonts.ongoing_music_font_size;
+    if ratio.is_finite() && ratio != 1.0 {
+        for mut text in query.iter_mut() {
+            text.style.font_size *= ratio;
+        }
+    };
+    fonts.ongoing_music_font_size = layout.font_size;
+    ongoing_music_setting.set_layout(&layout);
+    state.layout = Some(layout);
+}
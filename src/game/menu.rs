@@ -0,0 +1,59 @@
+use super::{absolute_text, color, despawn_all, AppState, Fonts, APP_STATE_STAGE};
+use crate::Theme;
+use bevy::{app::AppExit, prelude::*};
+
+pub fn build(app: &mut AppBuilder) {
+    app.on_state_enter(APP_STATE_STAGE, AppState::MainMenu, spawn_menu.system())
+        .on_state_update(
+            APP_STATE_STAGE,
+            AppState::MainMenu,
+            menu_key_system.system(),
+        )
+        .on_state_exit(
+            APP_STATE_STAGE,
+            AppState::MainMenu,
+            despawn_all::<MainMenuEntity>.system(),
+        );
+}
+
+/// an entity of the main menu
+struct MainMenuEntity;
+
+fn spawn_menu(commands: &mut Commands, fonts: Res<Fonts>, theme: Res<Theme>) {
+    let font_size = fonts.ongoing_music_font_size;
+    for (value, top, font_size) in &[
+        ("Music Typer", 100.0, font_size * 1.5),
+        ("Enter: choose a song", 400.0, font_size / 2.0),
+        ("S: settings", 450.0, font_size / 2.0),
+        ("Escape: quit", 500.0, font_size / 2.0),
+    ] {
+        let position = Rect {
+            top: Val::Px(*top),
+            left: Val::Px(100.0),
+            ..Default::default()
+        };
+        commands
+            .spawn(absolute_text(
+                value.to_string(),
+                position,
+                &fonts,
+                *font_size,
+                color(theme.text),
+            ))
+            .with(MainMenuEntity);
+    }
+}
+
+fn menu_key_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut state: ResMut<State<AppState>>,
+    mut app_exit_events: ResMut<Events<AppExit>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Return) {
+        state.set_next(AppState::SongSelect).unwrap();
+    } else if keyboard_input.just_pressed(KeyCode::S) {
+        state.set_next(AppState::Settings).unwrap();
+    } else if keyboard_input.just_pressed(KeyCode::Escape) {
+        app_exit_events.send(AppExit);
+    };
+}
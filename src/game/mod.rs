@@ -0,0 +1,768 @@
+mod countdown;
+mod input_field;
+mod layout;
+mod menu;
+mod playing;
+mod results;
+mod settings_menu;
+mod song_select;
+
+use crate::{ChordProSong, GameMode, Ghost, LibraryEntry, PlayingMusic, Replay, ReplayPlayer};
+use crate::{FontStack, ProgressTracker, RgbaColor, ScoreDatabase, ScoreRecord, SessionResult};
+use crate::{Settings, SettingsOverrides, Theme};
+use ab_glyph::Font as _;
+use bevy::asset::LoadState;
+use bevy::{prelude::*, window::WindowMode};
+use log::{error, info, warn};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::thread;
+
+pub use playing::OngoingMusicDisplaySetting;
+
+/// the screens of the game
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AppState {
+    /// nothing of the game is displayed. The game start in this state when the screens are
+    /// disabled, and go back to it when a song is finished or stopped.
+    Idle,
+    MainMenu,
+    SongSelect,
+    /// a short wait before a song start
+    Countdown,
+    Playing,
+    Paused,
+    Results,
+    /// the settings menu, opened from the main menu or over a paused song
+    Settings,
+}
+
+/// the stage the AppState systems run in
+pub const APP_STATE_STAGE: &str = "app_state";
+
+/// the typing game as a bevy plugin, to run it on its own or to embed the typing view in
+/// another app.
+///
+/// The host app drive the game with MusicTyperCommand events, and query it with the
+/// `State<AppState>`, OngoingMusic and LastResult resources. The SongFinished, PositionChanged
+/// and progress events are sent while a song is typed.
+pub struct MusicTyperPlugin {
+    /// the font, the theme, the sizes and the mode of the game. They can be changed while the
+    /// game run by changing the Settings resource.
+    pub settings: Settings,
+    /// the file the settings menu write the settings to, if any
+    pub settings_path: Option<PathBuf>,
+    /// the settings that replace the ones of settings for this run of the game only, like the
+    /// ones given on the command line. The settings menu doesn't save them.
+    pub overrides: SettingsOverrides,
+    /// the keys of the typing view. Its colors and sizes are replaced by the ones of the theme
+    /// and of the settings.
+    pub display: OngoingMusicDisplaySetting,
+    /// where the typed characters come from
+    pub input: InputSource,
+    /// the parts of the game that are added to the app
+    pub systems: MusicTyperSystems,
+    /// a command sent when the app start, like starting the song given on the command line
+    pub start: Option<MusicTyperCommand>,
+}
+
+impl Default for MusicTyperPlugin {
+    fn default() -> Self {
+        Self {
+            settings: Settings::default(),
+            settings_path: None,
+            overrides: SettingsOverrides::default(),
+            display: OngoingMusicDisplaySetting::default(),
+            input: InputSource::Keyboard,
+            systems: MusicTyperSystems::default(),
+            start: None,
+        }
+    }
+}
+
+impl Plugin for MusicTyperPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let systems = self.systems;
+        let initial_state = if systems.screens {
+            AppState::MainMenu
+        } else {
+            AppState::Idle
+        };
+        let settings = self.overrides.apply(&self.settings);
+        let theme = load_theme(&settings.theme);
+        app.add_resource(State::new(initial_state))
+            .add_stage_after(
+                stage::UPDATE,
+                APP_STATE_STAGE,
+                StateStage::<AppState>::default(),
+            )
+            .add_resource(systems)
+            .add_resource(self.input)
+            .add_resource(settings.clone())
+            .add_resource(SettingsFile {
+                path: self.settings_path.clone(),
+                overrides: self.overrides.clone(),
+                saved: self.settings.clone(),
+            })
+            .add_resource(StartCommand(self.start.clone()))
+            .add_resource(Fonts {
+                stack: font_stack(&settings, &theme),
+                ongoing_music_font_size: settings.font_size,
+                ..Default::default()
+            })
+            .add_resource(theme.clone())
+            .init_resource::<UiMaterials>()
+            .add_resource(OngoingMusic(None))
+            .add_resource(OngoingSong(None))
+            .add_resource(OngoingReplay(None))
+            .add_resource(OngoingGhost(None))
+            .add_resource(LastResult(None))
+            .add_event::<MusicTyperCommand>()
+            .add_event::<TypeCharacter>()
+            .add_event::<SongFinished>()
+            .add_event::<PositionChanged>()
+            .add_resource(OngoingProgress(None))
+            .add_event::<CharTyped>()
+            .add_event::<WordCompleted>()
+            .add_event::<LineCompleted>()
+            .add_event::<SectionCompleted>()
+            .add_event::<ComboBroken>()
+            .add_startup_system(setup.system())
+            .add_startup_system(send_start_command.system())
+            .add_system(command_system.system())
+            .add_system(apply_settings_system.system())
+            .add_system(apply_theme_system.system())
+            .add_system(font_load_system.system())
+            .add_system(last_result_system.system())
+            .add_system(log_song_finished_system.system());
+        if systems.camera {
+            app.add_startup_system(spawn_camera.system());
+        };
+        if systems.scores {
+            app.add_resource(Scores(ScoreDatabase::open_default()))
+                .add_system(save_score_system.system());
+        } else {
+            app.add_resource(Scores(None));
+        };
+        if systems.screens {
+            menu::build(app);
+            song_select::build(app, settings.library.clone());
+            countdown::build(app);
+            settings_menu::build(app);
+        };
+        let mut display = self.display.clone();
+        display.apply_settings(&settings, systems.window_layout);
+        display.apply_theme(&theme);
+        playing::build(app, display, systems);
+        input_field::build(app);
+        if systems.window_layout {
+            layout::build(app);
+        };
+        if systems.screens {
+            results::build(app);
+        };
+    }
+}
+
+/// the parts of the game that can be disabled, mostly when it is embedded in another app
+#[derive(Debug, Clone, Copy)]
+pub struct MusicTyperSystems {
+    /// the main menu, the song selection, the countdown and the results screen. Without them,
+    /// the songs are only started and stopped with MusicTyperCommand.
+    pub screens: bool,
+    /// spawn a camera for the user interface
+    pub camera: bool,
+    /// resize the texts and the lyrics to the size of the primary window
+    pub window_layout: bool,
+    /// save the finished runs in the score database
+    pub scores: bool,
+    /// display the live statistics while a song is typed
+    pub stats: bool,
+    /// send the CharTyped, WordCompleted, LineCompleted, SectionCompleted and ComboBroken events
+    pub progress_events: bool,
+}
+
+impl Default for MusicTyperSystems {
+    fn default() -> Self {
+        Self {
+            screens: true,
+            camera: true,
+            window_layout: true,
+            scores: true,
+            stats: true,
+            progress_events: true,
+        }
+    }
+}
+
+impl MusicTyperSystems {
+    /// the state to go to when the player leave a song
+    fn state_after_song(&self) -> AppState {
+        if self.screens {
+            AppState::SongSelect
+        } else {
+            AppState::Idle
+        }
+    }
+}
+
+/// where the typed characters come from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputSource {
+    /// the characters received by the windows
+    Keyboard,
+    /// only the TypeCharacter events sent by the host app
+    Events,
+}
+
+/// sent by the host app to type a character, when the InputSource is Events
+pub struct TypeCharacter(pub char);
+
+/// sent by the host app to control the game
+#[derive(Clone)]
+pub enum MusicTyperCommand {
+    /// start typing a song, after the countdown if the screens are enabled. It is ignored while
+    /// another song is typed or paused, that must be stopped first, like Replay.
+    Start(LibraryEntry),
+    /// play a replay of the given song
+    Replay(LibraryEntry, Replay),
+    /// leave the ongoing song without finishing it. It is kept in OngoingMusic so that it can
+    /// still be queried.
+    Stop,
+}
+
+#[derive(Default)]
+pub struct Fonts {
+    /// the fonts of the settings and of the theme, as paths in the assets folder
+    stack: FontStack,
+    /// the fonts of the lyrics: the stack, with the fonts given by the directives of the ongoing
+    /// song
+    lyrics_stack: FontStack,
+    /// the fonts requested to the asset server, by path
+    handles: HashMap<String, Handle<Font>>,
+    /// the fallback fonts that couldn't be loaded, and that are removed from the stacks
+    failed: HashSet<String>,
+    ongoing_music_font_size: f32,
+}
+
+impl Fonts {
+    fn music_font(&self) -> Handle<Font> {
+        self.handles
+            .get(&self.stack.main)
+            .cloned()
+            .expect("tried to use unitialized music font")
+    }
+
+    /// the font of the lyrics stack with this number, or the main font of the lyrics if it
+    /// wasn't requested
+    fn lyrics_font(&self, font: usize) -> Handle<Font> {
+        self.lyrics_stack
+            .path(font)
+            .and_then(|path| self.handles.get(path))
+            .or_else(|| self.handles.get(&self.lyrics_stack.main))
+            .cloned()
+            .unwrap_or_else(|| self.music_font())
+    }
+
+    /// split a text of the lyrics into the sections written with the same font of the lyrics
+    /// stack (see FontStack::split). The fonts that aren't loaded yet have no glyph.
+    fn split_lyrics(&self, font_assets: &Assets<Font>, text: &str) -> Vec<(String, usize)> {
+        self.lyrics_stack.split(text, |font, chara| {
+            self.lyrics_stack
+                .path(font)
+                .and_then(|path| self.handles.get(path))
+                .and_then(|handle| font_assets.get(handle))
+                .is_some_and(|font| font.font.glyph_id(chara).0 != 0)
+        })
+    }
+
+    fn load(&mut self, path: &str, asset_server: &AssetServer) {
+        if !self.handles.contains_key(path) {
+            self.handles
+                .insert(path.to_string(), asset_server.load(path));
+        };
+    }
+
+    /// use another stack, if it changed, with the fonts of the ongoing song
+    fn set_stack(
+        &mut self,
+        mut stack: FontStack,
+        song: Option<&ChordProSong>,
+        asset_server: &AssetServer,
+    ) {
+        let failed = &self.failed;
+        stack
+            .fallbacks
+            .retain(|fallback| !failed.contains(fallback));
+        if self.stack == stack && !self.handles.is_empty() {
+            return;
+        };
+        self.stack = stack;
+        self.load(&self.stack.main.clone(), asset_server);
+        self.set_song(song, asset_server);
+    }
+
+    /// take the fonts given by the song, and load every font of the lyrics stack: the font of
+    /// a character is chosen from the glyphs of the loaded fonts.
+    fn set_song(&mut self, song: Option<&ChordProSong>, asset_server: &AssetServer) {
+        let mut lyrics_stack = match song {
+            Some(song) => self.stack.with_song_fonts(&song.metadata),
+            None => self.stack.clone(),
+        };
+        let failed = &self.failed;
+        lyrics_stack
+            .fallbacks
+            .retain(|fallback| !failed.contains(fallback));
+        let paths: Vec<String> = lyrics_stack.paths().map(str::to_string).collect();
+        self.lyrics_stack = lyrics_stack;
+        for path in paths {
+            self.load(&path, asset_server);
+        }
+    }
+
+    /// true when every font requested for the lyrics is loaded, so that the lyrics can be
+    /// measured
+    fn lyrics_fonts_loaded(&self, font_assets: &Assets<Font>) -> bool {
+        self.lyrics_stack
+            .paths()
+            .filter_map(|path| self.handles.get(path))
+            .all(|handle| font_assets.get(handle).is_some())
+    }
+}
+
+/// the file the settings are saved in, with the settings that are overridden for this run and
+/// the values they had when the settings were last saved
+struct SettingsFile {
+    path: Option<PathBuf>,
+    overrides: SettingsOverrides,
+    saved: Settings,
+}
+
+/// the command sent when the app start
+struct StartCommand(Option<MusicTyperCommand>);
+
+/// the song that is typed, or that was typed last
+pub struct OngoingMusic(Option<PlayingMusic>);
+
+impl OngoingMusic {
+    pub fn playing_music(&self) -> Option<&PlayingMusic> {
+        self.0.as_ref()
+    }
+}
+
+/// the ChordPro song the OngoingMusic was created from, used to display its metadata and chords
+pub struct OngoingSong(Option<ChordProSong>);
+
+impl OngoingSong {
+    fn song_id(&self) -> String {
+        self.0
+            .as_ref()
+            .and_then(|song| song.metadata.title.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// the replay that currently drive the OngoingMusic, if any
+pub struct OngoingReplay(Option<ReplayPlayer>);
+
+/// a previous run the player race against, if any
+pub struct OngoingGhost(Option<Ghost>);
+
+/// sent once when the ongoing music is finished
+pub struct SongFinished(SessionResult);
+
+impl SongFinished {
+    pub fn result(&self) -> &SessionResult {
+        &self.0
+    }
+}
+
+/// sent every time the position in the lyrics of the ongoing music change, with the index of
+/// the source line and of the character in the line
+pub struct PositionChanged {
+    pub line: usize,
+    pub chara: usize,
+}
+
+/// follow the typing of the ongoing music to send the progress events below
+pub struct OngoingProgress(Option<ProgressTracker>);
+
+/// sent for every character added to the typed text of the ongoing music
+pub struct CharTyped {
+    /// false if the aligner consider the character wrong
+    pub correct: bool,
+}
+
+/// sent when the player typed past a word of the lyrics
+pub struct WordCompleted {
+    pub word: String,
+    /// the number of substituted, missing and extra characters in the word
+    pub errors: usize,
+}
+
+/// sent when every word of a line of the lyrics was completed
+pub struct LineCompleted {
+    pub line: usize,
+    /// the part of the characters of the line that were typed correctly
+    pub accuracy: f64,
+}
+
+/// sent when every line of a section of the song was completed
+pub struct SectionCompleted {
+    /// the index of the section in PlayingMusic::sections
+    pub section: usize,
+    pub correctness: f64,
+}
+
+/// sent when a wrong character end a series of correct characters
+pub struct ComboBroken {
+    /// the number of correct characters typed in a row before the wrong one
+    pub combo: usize,
+}
+
+/// the result of the last finished song
+pub struct LastResult(Option<SessionResult>);
+
+impl LastResult {
+    pub fn result(&self) -> Option<&SessionResult> {
+        self.0.as_ref()
+    }
+}
+
+/// the database the finished runs are saved in, if there is one
+pub struct Scores(Option<ScoreDatabase>);
+
+fn setup(mut fonts: ResMut<Fonts>, asset_server: Res<AssetServer>) {
+    let stack = fonts.stack.clone();
+    fonts.set_stack(stack, None, &asset_server);
+}
+
+/// remove the fallback fonts that can't be loaded from the stacks, so that the main font is used
+/// instead. The main font given by a song is replaced by the one of the settings.
+fn font_load_system(asset_server: Res<AssetServer>, mut fonts: ResMut<Fonts>) {
+    let is_failed = |path: &String| {
+        fonts.handles.get(path).map_or(false, |handle| {
+            asset_server.get_load_state(handle) == LoadState::Failed
+        })
+    };
+    let failed: Vec<String> = fonts
+        .lyrics_stack
+        .fallbacks
+        .iter()
+        .filter(|path| is_failed(path))
+        .cloned()
+        .collect();
+    let song_main_failed =
+        fonts.lyrics_stack.main != fonts.stack.main && is_failed(&fonts.lyrics_stack.main);
+    if song_main_failed {
+        warn!(
+            "can't load the font {:?} of the song, the main font is used instead",
+            fonts.lyrics_stack.main
+        );
+        fonts.lyrics_stack.main = fonts.stack.main.clone();
+    };
+    for path in failed {
+        warn!(
+            "can't load the font {:?}, the other fonts are used instead",
+            path
+        );
+        fonts.stack.remove(&path);
+        fonts.lyrics_stack.remove(&path);
+        fonts.failed.insert(path);
+    }
+}
+
+fn color(color: RgbaColor) -> Color {
+    let [red, green, blue, alpha] = color.0;
+    Color::rgba(red, green, blue, alpha)
+}
+
+/// the theme named in the settings, or the default theme if it can't be read
+fn load_theme(name: &str) -> Theme {
+    Theme::find(name).unwrap_or_else(|err| {
+        warn!(
+            "can't read the theme {:?}, using the default one: {}",
+            name, err
+        );
+        Theme::default()
+    })
+}
+
+/// the font of the theme, or else the one of the settings, with the fallback fonts of the
+/// settings
+fn font_stack(settings: &Settings, theme: &Theme) -> FontStack {
+    let main = theme.font.as_deref().unwrap_or(&settings.font);
+    FontStack::new(main, settings.fallback_fonts.clone())
+}
+
+fn window_mode(fullscreen: bool) -> WindowMode {
+    if fullscreen {
+        WindowMode::BorderlessFullscreen
+    } else {
+        WindowMode::Windowed
+    }
+}
+
+/// apply the changed settings that are not specific to the typing view, without restarting the
+/// ongoing song
+fn apply_settings_system(
+    settings: ChangedRes<Settings>,
+    systems: Res<MusicTyperSystems>,
+    asset_server: Res<AssetServer>,
+    ongoing_replay: Res<OngoingReplay>,
+    ongoing_song: Res<OngoingSong>,
+    mut theme: ResMut<Theme>,
+    mut fonts: ResMut<Fonts>,
+    mut windows: ResMut<Windows>,
+    mut ongoing_music: ResMut<OngoingMusic>,
+    mut query: Query<&mut Text>,
+) {
+    if theme.name != settings.theme {
+        *theme = load_theme(&settings.theme);
+    };
+    let stack = font_stack(&settings, &theme);
+    fonts.set_stack(stack, ongoing_song.0.as_ref(), &asset_server);
+    // else the font size follow the size of the window
+    if !systems.window_layout {
+        let ratio = settings.font_size / fonts.ongoing_music_font_size;
+        if ratio.is_finite() && ratio != 1.0 {
+            for mut text in query.iter_mut() {
+                text.style.font_size *= ratio;
+            }
+        };
+        fonts.ongoing_music_font_size = settings.font_size;
+    };
+    if systems.screens {
+        if let Some(window) = windows.get_primary_mut() {
+            let mode = window_mode(settings.fullscreen);
+            if window.mode() != mode {
+                window.set_mode(mode);
+            };
+        };
+    };
+    // a replay is played with the precision it was recorded with
+    if ongoing_replay.0.is_none() {
+        if let Some(music) = ongoing_music.0.as_mut() {
+            music.set_precision(settings.precision);
+        };
+    };
+}
+
+/// apply the changed theme to the materials and the background. The texts are colored by the
+/// systems of every screen.
+fn apply_theme_system(
+    theme: ChangedRes<Theme>,
+    settings: Res<Settings>,
+    systems: Res<MusicTyperSystems>,
+    asset_server: Res<AssetServer>,
+    ongoing_song: Res<OngoingSong>,
+    ui_materials: Res<UiMaterials>,
+    mut fonts: ResMut<Fonts>,
+    mut clear_color: ResMut<ClearColor>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let stack = font_stack(&settings, &theme);
+    fonts.set_stack(stack, ongoing_song.0.as_ref(), &asset_server);
+    for (handle, theme_color) in &[
+        (&ui_materials.button, theme.button),
+        (&ui_materials.button_hovered, theme.button_hovered),
+        (&ui_materials.background, theme.background),
+    ] {
+        if let Some(material) = materials.get_mut(*handle) {
+            material.color = color(*theme_color);
+        };
+    }
+    // else the host app own the background
+    if systems.screens {
+        clear_color.0 = color(theme.background);
+    };
+}
+
+fn send_start_command(
+    mut start_command: ResMut<StartCommand>,
+    mut commands: ResMut<Events<MusicTyperCommand>>,
+) {
+    if let Some(command) = start_command.0.take() {
+        commands.send(command);
+    };
+}
+
+fn spawn_camera(commands: &mut Commands) {
+    commands.spawn(UiCameraBundle::default());
+}
+
+/// apply the MusicTyperCommand sent by the host app
+fn command_system(
+    mut event_reader: Local<EventReader<MusicTyperCommand>>,
+    commands: Res<Events<MusicTyperCommand>>,
+    systems: Res<MusicTyperSystems>,
+    settings: Res<Settings>,
+    mut state: ResMut<State<AppState>>,
+    mut ongoing_music: ResMut<OngoingMusic>,
+    mut ongoing_song: ResMut<OngoingSong>,
+    mut ongoing_replay: ResMut<OngoingReplay>,
+    mut ongoing_ghost: ResMut<OngoingGhost>,
+) {
+    for command in event_reader.iter(&commands) {
+        match command {
+            MusicTyperCommand::Start(entry) | MusicTyperCommand::Replay(entry, _) => {
+                if let AppState::Countdown | AppState::Playing | AppState::Paused = state.current()
+                {
+                    warn!("can't start a song while another one is typed");
+                    continue;
+                };
+                let mut music = entry.to_playing_music();
+                music.set_precision(settings.precision);
+                let (player, next) = match command {
+                    MusicTyperCommand::Replay(_, replay) => {
+                        if !replay.is_for(&music) {
+                            warn!("the replay was recorded on another song");
+                            continue;
+                        };
+                        let mut player = ReplayPlayer::new(replay.clone());
+                        player.prepare(&mut music);
+                        (Some(player), AppState::Playing)
+                    }
+                    _ if systems.screens => (None, AppState::Countdown),
+                    _ => (None, AppState::Playing),
+                };
+                ongoing_music.0 = Some(music);
+                ongoing_song.0 = Some(entry.song.clone());
+                ongoing_replay.0 = player;
+                ongoing_ghost.0 = None;
+                if let Err(err) = state.overwrite_next(next) {
+                    error!("can't start the song: {:?}", err);
+                };
+            }
+            MusicTyperCommand::Stop => {
+                ongoing_replay.0 = None;
+                let next = systems.state_after_song();
+                if *state.current() == next {
+                    continue;
+                };
+                if let Err(err) = state.overwrite_next(next) {
+                    error!("can't stop the song: {:?}", err);
+                };
+            }
+        };
+    }
+}
+
+/// a text, to be placed by its parent
+fn text(value: String, fonts: &Fonts, font_size: f32, color: Color) -> TextBundle {
+    TextBundle {
+        text: Text {
+            value,
+            font: fonts.music_font(),
+            style: TextStyle {
+                font_size,
+                color,
+                ..Default::default()
+            },
+        },
+        ..Default::default()
+    }
+}
+
+/// a section of the lyrics, written with a font of the lyrics stack, to be placed by its parent
+fn lyrics_text(
+    value: String,
+    font: usize,
+    fonts: &Fonts,
+    font_size: f32,
+    color: Color,
+) -> TextBundle {
+    let mut text = text(value, fonts, font_size, color);
+    text.text.font = fonts.lyrics_font(font);
+    text
+}
+
+/// a text, placed at an absolute position
+fn absolute_text(
+    value: String,
+    position: Rect<Val>,
+    fonts: &Fonts,
+    font_size: f32,
+    color: Color,
+) -> TextBundle {
+    let mut text = text(value, fonts, font_size, color);
+    text.style.position_type = PositionType::Absolute;
+    text.style.position = position;
+    text
+}
+
+/// the materials of the nodes and buttons of the user interface
+pub struct UiMaterials {
+    transparent: Handle<ColorMaterial>,
+    button: Handle<ColorMaterial>,
+    button_hovered: Handle<ColorMaterial>,
+    /// the background of the screens displayed over the lyrics
+    background: Handle<ColorMaterial>,
+}
+
+impl FromResources for UiMaterials {
+    fn from_resources(resources: &Resources) -> Self {
+        let mut materials = resources.get_mut::<Assets<ColorMaterial>>().unwrap();
+        let theme = resources.get::<Theme>().unwrap();
+        Self {
+            transparent: materials.add(Color::NONE.into()),
+            button: materials.add(color(theme.button).into()),
+            button_hovered: materials.add(color(theme.button_hovered).into()),
+            background: materials.add(color(theme.background).into()),
+        }
+    }
+}
+
+/// despawn every entity with the component T, to be used when leaving a state
+fn despawn_all<T: Component>(commands: &mut Commands, query: Query<Entity, With<T>>) {
+    for entity in query.iter() {
+        commands.despawn_recursive(entity);
+    }
+}
+
+fn last_result_system(
+    mut event_reader: Local<EventReader<SongFinished>>,
+    song_finished_events: Res<Events<SongFinished>>,
+    mut last_result: ResMut<LastResult>,
+) {
+    for SongFinished(result) in event_reader.iter(&song_finished_events) {
+        last_result.0 = Some(result.clone());
+    }
+}
+
+fn save_score_system(
+    mut event_reader: Local<EventReader<SongFinished>>,
+    song_finished_events: Res<Events<SongFinished>>,
+    ongoing_music: Res<OngoingMusic>,
+    ongoing_song: Res<OngoingSong>,
+    scores: Res<Scores>,
+    settings: Res<Settings>,
+) {
+    for SongFinished(result) in event_reader.iter(&song_finished_events) {
+        if settings.mode == GameMode::Practice {
+            continue;
+        };
+        if let (Some(database), Some(music)) = (scores.0.as_ref(), ongoing_music.0.as_ref()) {
+            let song_id = ongoing_song.song_id();
+            let song_hash = music.song_hash();
+            let replay = Replay::from_playing_music(music, song_id.clone());
+            let record = ScoreRecord::new(replay.settings.clone(), result.clone());
+            let database = database.clone();
+            // writing the files can take a moment, which would freeze the game
+            thread::spawn(move || {
+                if let Err(err) = database.add_record(song_hash, &song_id, record, Some(&replay)) {
+                    error!("can't save the score in {:?}: {}", database.path(), err);
+                };
+            });
+        };
+    }
+}
+
+fn log_song_finished_system(
+    mut event_reader: Local<EventReader<SongFinished>>,
+    song_finished_events: Res<Events<SongFinished>>,
+) {
+    for SongFinished(result) in event_reader.iter(&song_finished_events) {
+        info!("song finished: {:?}", result);
+    }
+}
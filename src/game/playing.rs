@@ -0,0 +1,1336 @@
+use super::input_field::spawn_input_field;
+use super::APP_STATE_STAGE;
+use super::{absolute_text, color, despawn_all, lyrics_text, AppState, Fonts, UiMaterials};
+use super::{CharTyped, ComboBroken, LineCompleted, SectionCompleted, WordCompleted};
+use super::{InputSource, MusicTyperSystems, PositionChanged, SongFinished, TypeCharacter};
+use super::{OngoingGhost, OngoingMusic, OngoingProgress, OngoingReplay, OngoingSong};
+use crate::{AlignmentStatus, DiffRun, GameMode, PlayingMusic, COLUMN_WIDTH_RATIO};
+use crate::{ProgressEvent, ProgressTracker, Settings, Theme};
+use crate::{ScreenLayout, ScrollAnimation, ScrollEasing, WrappedLines};
+use ab_glyph::{Font as _, PxScale, ScaleFont};
+use bevy::{prelude::*, window::ReceivedCharacter};
+use std::collections::HashSet;
+use std::ops::Range;
+use std::time::Duration;
+use unicode_width::UnicodeWidthStr;
+
+pub fn build(
+    app: &mut AppBuilder,
+    ongoing_music_setting: OngoingMusicDisplaySetting,
+    systems: MusicTyperSystems,
+) {
+    app.add_resource(ongoing_music_setting)
+        .add_resource(OngoingMusicDisplayData::default())
+        .add_resource(WrappedLyrics::default())
+        .add_system(apply_display_settings_system.system())
+        .add_system(apply_display_theme_system.system())
+        .on_state_enter(
+            APP_STATE_STAGE,
+            AppState::Playing,
+            spawn_playing_screen.system(),
+        )
+        .on_state_exit(
+            APP_STATE_STAGE,
+            AppState::Playing,
+            despawn_playing_screen.system(),
+        )
+        .on_state_enter(
+            APP_STATE_STAGE,
+            AppState::Paused,
+            spawn_pause_overlay.system(),
+        )
+        .on_state_update(APP_STATE_STAGE, AppState::Paused, pause_key_system.system())
+        .on_state_exit(
+            APP_STATE_STAGE,
+            AppState::Paused,
+            despawn_all::<PauseOverlay>.system(),
+        )
+        .on_state_exit(
+            APP_STATE_STAGE,
+            AppState::Paused,
+            despawn_playing_screen.system(),
+        )
+        .on_state_update(
+            APP_STATE_STAGE,
+            AppState::Playing,
+            ongoing_music_input_system.system(),
+        )
+        .on_state_update(
+            APP_STATE_STAGE,
+            AppState::Playing,
+            ongoing_music_time_system.system(),
+        )
+        .on_state_update(
+            APP_STATE_STAGE,
+            AppState::Playing,
+            ongoing_music_finish_key_system.system(),
+        )
+        .on_state_update(
+            APP_STATE_STAGE,
+            AppState::Playing,
+            playing_escape_system.system(),
+        )
+        .on_state_update(
+            APP_STATE_STAGE,
+            AppState::Playing,
+            song_finished_system.system(),
+        )
+        .on_state_update(
+            APP_STATE_STAGE,
+            AppState::Playing,
+            replay_control_system.system(),
+        )
+        .on_state_update(
+            APP_STATE_STAGE,
+            AppState::Playing,
+            replay_advance_system.system(),
+        )
+        .on_state_update(
+            APP_STATE_STAGE,
+            AppState::Playing,
+            ghost_sync_system.system(),
+        )
+        .on_state_update(
+            APP_STATE_STAGE,
+            AppState::Playing,
+            move_ghost_display_system.system(),
+        )
+        .on_state_update(
+            APP_STATE_STAGE,
+            AppState::Playing,
+            position_changed_system.system(),
+        )
+        .on_state_update(
+            APP_STATE_STAGE,
+            AppState::Playing,
+            wrap_lyrics_system.system(),
+        )
+        // the children of the rows are spawned before the rows that left the screen are
+        // despawned
+        .on_state_update(
+            APP_STATE_STAGE,
+            AppState::Playing,
+            update_music_line_runs_system.system(),
+        )
+        .on_state_update(
+            APP_STATE_STAGE,
+            AppState::Playing,
+            move_music_text_system.system(),
+        )
+        .on_state_update(
+            APP_STATE_STAGE,
+            AppState::Playing,
+            fade_music_lines_system.system(),
+        );
+    if systems.progress_events {
+        app.on_state_update(
+            APP_STATE_STAGE,
+            AppState::Playing,
+            progress_events_system.system(),
+        );
+    };
+    if systems.stats {
+        app.on_state_update(
+            APP_STATE_STAGE,
+            AppState::Playing,
+            update_stats_text.system(),
+        );
+    };
+}
+
+/// an entity of the playing screen, kept while the game is paused
+pub struct PlayingEntity;
+
+/// the text that display the live statistics of the ongoing music
+struct OngoingMusicStatsMarker;
+
+/// the title and the artist of the ongoing music
+struct PlayingHeader;
+
+/// the statistics are recomputed every time this timer finish
+struct StatsUpdateTimer(Timer);
+
+impl Default for StatsUpdateTimer {
+    fn default() -> Self {
+        Self(Timer::from_seconds(0.25, true))
+    }
+}
+
+/// a visual row of the lyrics, the usize is the row number in WrappedLyrics
+struct MusicDisplayedLine(usize);
+
+/// the runs of text, with their color, a displayed line is made of. None until it is displayed
+/// for the first time.
+struct DisplayedRuns(Option<Vec<(String, Color, usize)>>);
+
+/// the opacity of a displayed line, lowered when it enters or leaves the displayed lines
+struct LineOpacity(f32);
+
+/// the chords displayed above a visual row of the lyrics, the usize is the row number
+struct MusicDisplayedChords(usize);
+
+/// the entities that display the ghost
+#[derive(Clone, Copy, PartialEq)]
+enum GhostDisplay {
+    /// placed at the position of the ghost in the lyrics
+    Marker,
+    /// display how much the player is ahead of the ghost
+    Lead,
+}
+
+/// the text displayed while the game is paused
+struct PauseOverlay;
+
+/// spawn the lyrics, the chords, the typed text and the statistics of the ongoing music. Nothing
+/// is spawned when resuming from a pause, as they are kept while paused.
+fn spawn_playing_screen(
+    commands: &mut Commands,
+    state: Res<State<AppState>>,
+    ongoing_music: Res<OngoingMusic>,
+    ongoing_song: Res<OngoingSong>,
+    asset_server: Res<AssetServer>,
+    mut fonts: ResMut<Fonts>,
+    ui_materials: Res<UiMaterials>,
+    ongoing_music_setting: Res<OngoingMusicDisplaySetting>,
+    systems: Res<MusicTyperSystems>,
+    mut wrapped_lyrics: ResMut<WrappedLyrics>,
+    mut ongoing_progress: ResMut<OngoingProgress>,
+) {
+    if state.previous() == Some(&AppState::Paused) {
+        return;
+    };
+    ongoing_progress.0 = ongoing_music.0.as_ref().map(ProgressTracker::new);
+    fonts.set_song(ongoing_song.0.as_ref(), &asset_server);
+    let font_size = fonts.ongoing_music_font_size;
+    // the lines and the chords are spawned by wrap_lyrics_system
+    wrapped_lyrics.lines = None;
+    if let Some(song) = ongoing_song.0.as_ref() {
+        let header = match (&song.metadata.title, &song.metadata.artist) {
+            (Some(title), Some(artist)) => format!("{} - {}", title, artist),
+            (Some(title), None) => title.clone(),
+            (None, Some(artist)) => artist.clone(),
+            (None, None) => String::new(),
+        };
+        let position = Rect {
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..Default::default()
+        };
+        commands
+            .spawn(absolute_text(
+                header,
+                position,
+                &fonts,
+                font_size / 2.0,
+                ongoing_music_setting.non_current_color,
+            ))
+            .with(PlayingHeader)
+            .with(PlayingEntity);
+    };
+    spawn_input_field(commands, &fonts, &ui_materials, &ongoing_music_setting);
+    if systems.stats {
+        let position = Rect {
+            top: Val::Px(10.0),
+            right: Val::Px(10.0),
+            ..Default::default()
+        };
+        commands
+            .spawn(absolute_text(
+                "".into(),
+                position,
+                &fonts,
+                font_size / 2.0,
+                ongoing_music_setting.non_current_color,
+            ))
+            .with(OngoingMusicStatsMarker)
+            .with(PlayingEntity);
+    };
+    for (value, font_size, marker) in &[
+        (">", font_size, GhostDisplay::Marker),
+        ("", font_size / 2.0, GhostDisplay::Lead),
+    ] {
+        let position = Rect {
+            right: Val::Px(10.0),
+            ..Default::default()
+        };
+        let mut text = absolute_text(
+            value.to_string(),
+            position,
+            &fonts,
+            *font_size,
+            ongoing_music_setting.ghost_color,
+        );
+        text.draw.is_visible = false;
+        commands.spawn(text).with(*marker).with(PlayingEntity);
+    }
+}
+
+/// despawn the playing screen, unless the game is only paused or resumed
+fn despawn_playing_screen(
+    commands: &mut Commands,
+    state: Res<State<AppState>>,
+    query: Query<Entity, With<PlayingEntity>>,
+) {
+    match state.next() {
+        // the settings can be changed over a paused song
+        Some(AppState::Playing) | Some(AppState::Paused) | Some(AppState::Settings) => (),
+        _ => despawn_all::<PlayingEntity>(commands, query),
+    };
+}
+
+fn spawn_pause_overlay(
+    commands: &mut Commands,
+    fonts: Res<Fonts>,
+    theme: Res<Theme>,
+    systems: Res<MusicTyperSystems>,
+) {
+    let font_size = fonts.ongoing_music_font_size;
+    let help = if systems.screens {
+        "Escape: resume  Enter: back to the song selection  S: settings"
+    } else {
+        "Escape: resume  Enter: back to the song selection"
+    };
+    for (value, top, font_size) in &[("Paused", 200.0, font_size), (help, 300.0, font_size / 2.0)] {
+        let position = Rect {
+            top: Val::Px(*top),
+            left: Val::Px(100.0),
+            ..Default::default()
+        };
+        commands
+            .spawn(absolute_text(
+                value.to_string(),
+                position,
+                &fonts,
+                *font_size,
+                color(theme.emphasis),
+            ))
+            .with(PauseOverlay);
+    }
+}
+
+fn pause_key_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    systems: Res<MusicTyperSystems>,
+    mut state: ResMut<State<AppState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        state.set_next(AppState::Playing).unwrap();
+    } else if keyboard_input.just_pressed(KeyCode::Return) {
+        state.set_next(systems.state_after_song()).unwrap();
+    } else if systems.screens && keyboard_input.just_pressed(KeyCode::S) {
+        state.set_next(AppState::Settings).unwrap();
+    };
+}
+
+#[derive(Default)]
+struct CharInputState {
+    event_reader: EventReader<ReceivedCharacter>,
+    type_character_reader: EventReader<TypeCharacter>,
+}
+
+/// type the characters of the InputSource in the ongoing music
+fn ongoing_music_input_system(
+    mut ongoing_music: ResMut<OngoingMusic>,
+    ongoing_replay: Res<OngoingReplay>,
+    input_source: Res<InputSource>,
+    mut state: Local<CharInputState>,
+    char_input_events: Res<Events<ReceivedCharacter>>,
+    type_character_events: Res<Events<TypeCharacter>>,
+) {
+    let state = &mut *state;
+    let received_chars = state
+        .event_reader
+        .iter(&char_input_events)
+        .map(|event| event.char);
+    let sent_chars = state
+        .type_character_reader
+        .iter(&type_character_events)
+        .map(|TypeCharacter(chara)| *chara);
+    let chars: Vec<char> = match *input_source {
+        InputSource::Keyboard => received_chars.collect(),
+        InputSource::Events => sent_chars.collect(),
+    };
+    if ongoing_replay.0.is_some() {
+        return;
+    };
+    if let Some(music) = ongoing_music.0.as_mut() {
+        for chara in chars {
+            music.add_typed_char(chara);
+        }
+    };
+}
+
+/// advance the clock of the ongoing music
+fn ongoing_music_time_system(
+    time: Res<Time>,
+    ongoing_replay: Res<OngoingReplay>,
+    mut ongoing_music: ResMut<OngoingMusic>,
+) {
+    if ongoing_replay.0.is_some() {
+        return;
+    };
+    if let Some(music) = ongoing_music.0.as_mut() {
+        if !music.is_finished() {
+            let elapsed_time = music.elapsed_time() + Duration::from_secs_f32(time.delta_seconds());
+            music.set_elapsed_time(elapsed_time);
+        };
+    };
+}
+
+fn ongoing_music_finish_key_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    ongoing_music_setting: Res<OngoingMusicDisplaySetting>,
+    ongoing_replay: Res<OngoingReplay>,
+    mut ongoing_music: ResMut<OngoingMusic>,
+) {
+    if ongoing_replay.0.is_none() && keyboard_input.just_pressed(ongoing_music_setting.finish_key) {
+        if let Some(music) = ongoing_music.0.as_mut() {
+            music.finish();
+        };
+    };
+}
+
+/// pause the game, or stop the ongoing replay and go back to the results
+fn playing_escape_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut state: ResMut<State<AppState>>,
+    mut ongoing_music: ResMut<OngoingMusic>,
+    mut ongoing_replay: ResMut<OngoingReplay>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::Escape) {
+        return;
+    };
+    match ongoing_replay.0.take() {
+        Some(mut player) => {
+            // leave the music as it was at the end of the run
+            if let Some(music) = ongoing_music.0.as_mut() {
+                let duration = player.replay().duration();
+                player.seek(music, duration);
+            };
+            state.set_next(AppState::Results).unwrap();
+        }
+        None => state.set_next(AppState::Paused).unwrap(),
+    };
+}
+
+fn song_finished_system(
+    ongoing_music: Res<OngoingMusic>,
+    ongoing_replay: Res<OngoingReplay>,
+    mut was_finished: Local<bool>,
+    systems: Res<MusicTyperSystems>,
+    mut song_finished_events: ResMut<Events<SongFinished>>,
+    mut state: ResMut<State<AppState>>,
+) {
+    let result = ongoing_music.0.as_ref().and_then(|music| music.result());
+    // a replay doesn't finish a song
+    if ongoing_replay.0.is_some() {
+        *was_finished = result.is_some();
+        return;
+    };
+    match result {
+        Some(result) => {
+            if !*was_finished {
+                song_finished_events.send(SongFinished(result));
+                let next = if systems.screens {
+                    AppState::Results
+                } else {
+                    AppState::Idle
+                };
+                state.set_next(next).unwrap();
+            };
+            *was_finished = true;
+        }
+        None => *was_finished = false,
+    };
+}
+
+/// play, pause or seek the ongoing replay
+fn replay_control_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut ongoing_music: ResMut<OngoingMusic>,
+    mut ongoing_replay: ResMut<OngoingReplay>,
+) {
+    let seek_step = Duration::from_secs(5);
+    if let (Some(player), Some(music)) = (ongoing_replay.0.as_mut(), ongoing_music.0.as_mut()) {
+        if keyboard_input.just_pressed(KeyCode::Space) {
+            player.toggle_pause();
+        };
+        if keyboard_input.just_pressed(KeyCode::Left) {
+            let time = player.clock().checked_sub(seek_step).unwrap_or_default();
+            player.seek(music, time);
+        };
+        if keyboard_input.just_pressed(KeyCode::Right) {
+            let time = player.clock() + seek_step;
+            player.seek(music, time);
+        };
+        if keyboard_input.just_pressed(KeyCode::Up) {
+            player.set_speed(player.speed() * 2.0);
+        };
+        if keyboard_input.just_pressed(KeyCode::Down) {
+            player.set_speed(player.speed() / 2.0);
+        };
+    };
+}
+
+fn replay_advance_system(
+    time: Res<Time>,
+    mut ongoing_music: ResMut<OngoingMusic>,
+    mut ongoing_replay: ResMut<OngoingReplay>,
+) {
+    if let (Some(player), Some(music)) = (ongoing_replay.0.as_mut(), ongoing_music.0.as_mut()) {
+        player.advance(music, Duration::from_secs_f32(time.delta_seconds()));
+    };
+}
+
+/// send the progress events of the ongoing music
+fn progress_events_system(
+    ongoing_music: Res<OngoingMusic>,
+    mut ongoing_progress: ResMut<OngoingProgress>,
+    mut char_typed_events: ResMut<Events<CharTyped>>,
+    mut word_completed_events: ResMut<Events<WordCompleted>>,
+    mut line_completed_events: ResMut<Events<LineCompleted>>,
+    mut section_completed_events: ResMut<Events<SectionCompleted>>,
+    mut combo_broken_events: ResMut<Events<ComboBroken>>,
+) {
+    let (playing_music, tracker) = match (ongoing_music.0.as_ref(), ongoing_progress.0.as_mut()) {
+        (Some(playing_music), Some(tracker)) => (playing_music, tracker),
+        _ => return,
+    };
+    for event in tracker.update(playing_music) {
+        match event {
+            ProgressEvent::CharTyped { correct } => char_typed_events.send(CharTyped { correct }),
+            ProgressEvent::WordCompleted { word, errors } => {
+                word_completed_events.send(WordCompleted { word, errors })
+            }
+            ProgressEvent::LineCompleted { line, accuracy } => {
+                line_completed_events.send(LineCompleted { line, accuracy })
+            }
+            ProgressEvent::SectionCompleted {
+                section,
+                correctness,
+            } => section_completed_events.send(SectionCompleted {
+                section,
+                correctness,
+            }),
+            ProgressEvent::ComboBroken { combo } => combo_broken_events.send(ComboBroken { combo }),
+        };
+    }
+}
+
+/// send a PositionChanged event every time the position in the lyrics of the ongoing music
+/// change
+fn position_changed_system(
+    ongoing_music: Res<OngoingMusic>,
+    mut last_position: Local<Option<(usize, usize)>>,
+    mut position_events: ResMut<Events<PositionChanged>>,
+) {
+    if let Some(playing_music) = ongoing_music.0.as_ref() {
+        let position = (
+            playing_music.position_in_source_lines(),
+            playing_music.position_in_source_line(),
+        );
+        if *last_position != Some(position) {
+            *last_position = Some(position);
+            position_events.send(PositionChanged {
+                line: position.0,
+                chara: position.1,
+            });
+        };
+    };
+}
+
+/// drive the ghost on the clock of the live session
+fn ghost_sync_system(ongoing_music: Res<OngoingMusic>, mut ongoing_ghost: ResMut<OngoingGhost>) {
+    if let (Some(ghost), Some(music)) = (ongoing_ghost.0.as_mut(), ongoing_music.0.as_ref()) {
+        ghost.sync(music.elapsed_time());
+    };
+}
+
+/// place the ghost marker next to the line the ghost is on, and show how far ahead of the ghost
+/// the player is. Both are hidden when there is no ghost.
+fn move_ghost_display_system(
+    ongoing_music: Res<OngoingMusic>,
+    ongoing_ghost: Res<OngoingGhost>,
+    ongoing_music_setting: Res<OngoingMusicDisplaySetting>,
+    ongoing_music_data: Res<OngoingMusicDisplayData>,
+    wrapped_lyrics: Res<WrappedLyrics>,
+    mut query: Query<(&GhostDisplay, &mut Style, &mut Text, &mut Draw)>,
+) {
+    let (ghost, music) = match (ongoing_ghost.0.as_ref(), ongoing_music.0.as_ref()) {
+        (Some(ghost), Some(music)) => (ghost, music),
+        _ => {
+            for (_, _, _, mut draw) in query.iter_mut() {
+                draw.is_visible = false;
+            }
+            return;
+        }
+    };
+    let ghost_placement = wrapped_lyrics.lines.as_ref().and_then(|lines| {
+        let ghost_row = current_row(ghost.music(), lines);
+        line_placement(ghost_row, &ongoing_music_setting, &ongoing_music_data)
+    });
+    for (display, mut style, mut text, mut draw) in query.iter_mut() {
+        match display {
+            GhostDisplay::Marker => match ghost_placement {
+                Some((top, _)) => {
+                    draw.is_visible = true;
+                    style.position.top = Val::Px(top);
+                }
+                None => draw.is_visible = false,
+            },
+            GhostDisplay::Lead => {
+                let lead = ghost.lead(music);
+                draw.is_visible = true;
+                style.position.top = Val::Px(ongoing_music_setting.current_y / 4.0);
+                text.value = format!("ghost: {:+} chars  {:+.1} s", lead.chars, lead.seconds);
+            }
+        }
+    }
+}
+
+/// the colors, the keys and the layout of the typing view
+#[derive(Clone)]
+pub struct OngoingMusicDisplaySetting {
+    /// the color of the untyped characters of the current line
+    pub current_color: Color,
+    /// the color of the typed text in the input field
+    pub typed_color: Color,
+    /// the color of the marker of a space that was typed but is not in the typed text yet
+    pub pending_space_color: Color,
+    /// the width of the input field, in columns
+    pub input_field_columns: usize,
+    /// the colors of the characters, depending on their alignment status
+    pub correct_color: Color,
+    pub substituted_color: Color,
+    pub missing_color: Color,
+    pub extra_color: Color,
+    pub untyped_color: Color,
+    /// the color of the characters that aren't typed, like section headings
+    pub not_typed_color: Color,
+    /// the color of the caret placed at the aligned position in the current line, and of the
+    /// caret of the input field
+    pub caret_color: Color,
+    /// the color of the lines that aren't typed yet, and of the header and the statistics
+    pub non_current_color: Color,
+    pub chords_color: Color,
+    pub section_heading_color: Color,
+    pub show_chords: bool,
+    /// the key that finish the ongoing song
+    pub finish_key: KeyCode,
+    /// the key that replay the song that was just finished
+    pub replay_key: KeyCode,
+    /// the key that restart the song that was just finished, racing against the previous run
+    pub ghost_key: KeyCode,
+    pub ghost_color: Color,
+    /// the vertical distance between two lines of lyrics
+    pub distance_between_line: f32,
+    /// the width longer lines are wrapped at
+    pub max_line_width: f32,
+    pub current_y: f32,
+    /// the number of lines displayed above the current line
+    pub top_displayed_line: usize,
+    /// the number of lines displayed below the current line
+    pub bottom_displayed_line: usize,
+    /// how the lyrics scroll when the current line change
+    pub scroll_easing: ScrollEasing,
+    /// the number of lines over which the lines entering or leaving the displayed lines fade
+    pub fade_lines: f32,
+}
+
+impl Default for OngoingMusicDisplaySetting {
+    fn default() -> Self {
+        let settings = Settings::default();
+        let theme = Theme::default();
+        let colors = &theme.typing;
+        let display = &settings.display;
+        Self {
+            current_color: color(colors.current),
+            typed_color: color(colors.typed),
+            pending_space_color: color(colors.pending_space),
+            input_field_columns: 40,
+            correct_color: color(colors.correct),
+            substituted_color: color(colors.substituted),
+            missing_color: color(colors.missing),
+            extra_color: color(colors.extra),
+            untyped_color: color(colors.untyped),
+            not_typed_color: color(colors.not_typed),
+            caret_color: color(colors.caret),
+            non_current_color: color(colors.non_current),
+            chords_color: color(colors.chords),
+            section_heading_color: color(colors.section_heading),
+            show_chords: display.show_chords,
+            finish_key: KeyCode::End,
+            replay_key: KeyCode::F5,
+            ghost_key: KeyCode::F6,
+            ghost_color: color(colors.ghost),
+            distance_between_line: display.distance_between_line,
+            max_line_width: 1920.0 - settings.font_size,
+            current_y: 1080.0 / 2.0,
+            top_displayed_line: display.top_displayed_line,
+            bottom_displayed_line: display.bottom_displayed_line,
+            scroll_easing: ScrollEasing::default(),
+            fade_lines: display.fade_lines,
+        }
+    }
+}
+
+impl OngoingMusicDisplaySetting {
+    /// place the lyrics according to the layout of the window
+    pub fn set_layout(&mut self, layout: &ScreenLayout) {
+        self.distance_between_line = layout.distance_between_line;
+        self.current_y = layout.current_y;
+        self.top_displayed_line = layout.top_displayed_line;
+        self.bottom_displayed_line = layout.bottom_displayed_line;
+        self.input_field_columns = layout.input_field_columns;
+        self.max_line_width = layout.max_line_width;
+    }
+
+    /// take the display settings. The distance between lines and the numbers of lines are only
+    /// taken when the layout doesn't follow the size of the window.
+    pub fn apply_settings(&mut self, settings: &Settings, window_layout: bool) {
+        let display = &settings.display;
+        self.show_chords = display.show_chords;
+        self.fade_lines = display.fade_lines;
+        if !window_layout {
+            self.distance_between_line = display.distance_between_line;
+            self.top_displayed_line = display.top_displayed_line;
+            self.bottom_displayed_line = display.bottom_displayed_line;
+        };
+    }
+
+    /// take the colors of the typing view from the theme
+    pub fn apply_theme(&mut self, theme: &Theme) {
+        let colors = &theme.typing;
+        self.current_color = color(colors.current);
+        self.typed_color = color(colors.typed);
+        self.pending_space_color = color(colors.pending_space);
+        self.correct_color = color(colors.correct);
+        self.substituted_color = color(colors.substituted);
+        self.missing_color = color(colors.missing);
+        self.extra_color = color(colors.extra);
+        self.untyped_color = color(colors.untyped);
+        self.not_typed_color = color(colors.not_typed);
+        self.caret_color = color(colors.caret);
+        self.non_current_color = color(colors.non_current);
+        self.chords_color = color(colors.chords);
+        self.section_heading_color = color(colors.section_heading);
+        self.ghost_color = color(colors.ghost);
+    }
+
+    pub fn status_color(&self, status: Option<AlignmentStatus>) -> Color {
+        match status {
+            Some(AlignmentStatus::Correct) => self.correct_color,
+            Some(AlignmentStatus::Substituted) => self.substituted_color,
+            Some(AlignmentStatus::Missing) => self.missing_color,
+            Some(AlignmentStatus::Extra) => self.extra_color,
+            Some(AlignmentStatus::Untyped) => self.untyped_color,
+            None => self.not_typed_color,
+        }
+    }
+}
+
+struct OngoingMusicDisplayData {
+    /// the y coordinate of the first line of the song, if it was displayed, following the
+    /// current line
+    scroll: ScrollAnimation,
+}
+
+impl Default for OngoingMusicDisplayData {
+    fn default() -> Self {
+        Self {
+            scroll: ScrollAnimation::new(ScrollEasing::default(), 0.0),
+        }
+    }
+}
+
+/// the lines of the ongoing music, wrapped so that they fit in the window. None until they are
+/// wrapped and displayed by wrap_lyrics_system.
+#[derive(Default)]
+struct WrappedLyrics {
+    lines: Option<WrappedLines>,
+    /// the chords displayed above every row, empty if there isn't any
+    chords: Vec<String>,
+    /// incremented every time the lines are wrapped again
+    generation: usize,
+    /// the font size and the width the lines were wrapped for
+    font_size: f32,
+    max_line_width: f32,
+    /// false if the fonts weren't loaded yet, and the width of the text was estimated
+    measured: bool,
+}
+
+/// the width of a text written with a font, estimated from its number of columns if the font
+/// isn't loaded yet
+fn text_width(font: Option<&Font>, font_size: f32, value: &str) -> f32 {
+    let font = match font {
+        Some(font) => font.font.as_scaled(PxScale::from(font_size)),
+        None => return UnicodeWidthStr::width(value) as f32 * font_size * COLUMN_WIDTH_RATIO,
+    };
+    let mut width = 0.0;
+    let mut previous = None;
+    for chara in value.chars() {
+        let glyph_id = font.glyph_id(chara);
+        if let Some(previous) = previous {
+            width += font.kern(previous, glyph_id);
+        };
+        width += font.h_advance(glyph_id);
+        previous = Some(glyph_id);
+    }
+    width
+}
+
+/// the width of a text of the lyrics, every section being measured with its font
+fn lyrics_width(fonts: &Fonts, font_assets: &Assets<Font>, value: &str) -> f32 {
+    fonts
+        .split_lyrics(font_assets, value)
+        .iter()
+        .map(|(section, font)| {
+            let font = font_assets.get(&fonts.lyrics_font(*font));
+            text_width(font, fonts.ongoing_music_font_size, section)
+        })
+        .sum()
+}
+
+/// split the runs of a row into the sections written with the same font, with the number of
+/// their font in the lyrics stack
+fn font_sections(
+    runs: &[(String, Color)],
+    fonts: &Fonts,
+    font_assets: &Assets<Font>,
+) -> Vec<(String, Color, usize)> {
+    runs.iter()
+        .flat_map(|(value, color)| {
+            fonts
+                .split_lyrics(font_assets, value)
+                .into_iter()
+                .map(move |(section, font)| (section, *color, font))
+        })
+        .collect()
+}
+
+/// wrap the lines of the ongoing music when it start, and when the window or the font change.
+/// The rows are then displayed by move_music_text_system.
+fn wrap_lyrics_system(
+    ongoing_music: Res<OngoingMusic>,
+    ongoing_song: Res<OngoingSong>,
+    ongoing_music_setting: Res<OngoingMusicDisplaySetting>,
+    fonts: Res<Fonts>,
+    font_assets: Res<Assets<Font>>,
+    mut wrapped_lyrics: ResMut<WrappedLyrics>,
+    mut ongoing_music_data: ResMut<OngoingMusicDisplayData>,
+) {
+    let playing_music = match ongoing_music.0.as_ref() {
+        Some(playing_music) => playing_music,
+        None => return,
+    };
+    let font_size = fonts.ongoing_music_font_size;
+    let loaded = fonts.lyrics_fonts_loaded(&font_assets);
+    if wrapped_lyrics.lines.is_some()
+        && wrapped_lyrics.font_size == font_size
+        && wrapped_lyrics.max_line_width == ongoing_music_setting.max_line_width
+        && (wrapped_lyrics.measured || !loaded)
+    {
+        return;
+    };
+    let lines = WrappedLines::new(
+        playing_music.lines(),
+        ongoing_music_setting.max_line_width,
+        |value| lyrics_width(&fonts, &font_assets, value),
+    );
+    let mut chords = vec![String::new(); lines.rows().len()];
+    if let (Some(song), true) = (ongoing_song.0.as_ref(), ongoing_music_setting.show_chords) {
+        for (line_count, line) in song.lines.iter().enumerate() {
+            if line.chords.is_empty() {
+                continue;
+            };
+            let chords_rows = lines.split_aligned_text(line_count, &line.chords_text());
+            for (row_count, row_chords) in lines.rows_of_line(line_count).zip(chords_rows) {
+                chords[row_count] = row_chords;
+            }
+        }
+    };
+    ongoing_music_data.scroll = ScrollAnimation::new(
+        ongoing_music_setting.scroll_easing,
+        target_y_coordinate(current_row(playing_music, &lines), &ongoing_music_setting),
+    );
+    *wrapped_lyrics = WrappedLyrics {
+        lines: Some(lines),
+        chords,
+        generation: wrapped_lyrics.generation + 1,
+        font_size,
+        max_line_width: ongoing_music_setting.max_line_width,
+        measured: loaded,
+    };
+}
+
+/// the visual row the position of the PlayingMusic is on
+fn current_row(playing_music: &PlayingMusic, lines: &WrappedLines) -> usize {
+    lines.row_at(
+        playing_music.position_in_source_lines(),
+        playing_music.position_in_source_line(),
+    )
+}
+
+/// the y coordinate of the first row of the song once the lyrics stop scrolling, so that the
+/// current row is displayed just below current_y
+fn target_y_coordinate(
+    current_row: usize,
+    ongoing_music_setting: &OngoingMusicDisplaySetting,
+) -> f32 {
+    ongoing_music_setting.current_y
+        - current_row as f32 * ongoing_music_setting.distance_between_line
+}
+
+/// return the top coordinate of the given row and its opacity, or None if it shouldn't be
+/// displayed. The rows already typed are above current_y, the others below it.
+fn line_placement(
+    row_count: usize,
+    ongoing_music_setting: &OngoingMusicDisplaySetting,
+    ongoing_music_data: &OngoingMusicDisplayData,
+) -> Option<(f32, f32)> {
+    let distance_between_line = ongoing_music_setting.distance_between_line;
+    // the position relative to the current row, fractional while scrolling
+    let difference = row_count as f32
+        + (ongoing_music_data.scroll.position() - ongoing_music_setting.current_y)
+            / distance_between_line;
+    let outside = (-(ongoing_music_setting.top_displayed_line as f32) - difference)
+        .max(difference - ongoing_music_setting.bottom_displayed_line as f32)
+        .max(0.0);
+    let opacity = if ongoing_music_setting.fade_lines > 0.0 {
+        1.0 - outside / ongoing_music_setting.fade_lines
+    } else if outside > 0.0 {
+        0.0
+    } else {
+        1.0
+    };
+    if opacity <= 0.0 {
+        return None;
+    };
+    // leave a line between the typed lines and the others for the input field
+    let input_field_gap = (difference + 1.0).max(0.0).min(1.0);
+    Some((
+        (difference + input_field_gap) * distance_between_line + ongoing_music_setting.current_y,
+        opacity,
+    ))
+}
+
+/// the given color, made more transparent by the opacity
+fn faded(color: Color, opacity: f32) -> Color {
+    let mut color = color;
+    color.set_a(color.a() * opacity);
+    color
+}
+
+/// the rows that can be displayed, entirely or fading, at the actual scroll position
+fn visible_rows(
+    row_len: usize,
+    ongoing_music_setting: &OngoingMusicDisplaySetting,
+    ongoing_music_data: &OngoingMusicDisplayData,
+) -> Range<usize> {
+    let offset = (ongoing_music_data.scroll.position() - ongoing_music_setting.current_y)
+        / ongoing_music_setting.distance_between_line;
+    let first = -(ongoing_music_setting.top_displayed_line as f32)
+        - ongoing_music_setting.fade_lines
+        - offset;
+    let end = ongoing_music_setting.bottom_displayed_line as f32 + ongoing_music_setting.fade_lines
+        - offset
+        + 1.0;
+    let first = (first.ceil().max(0.0) as usize).min(row_len);
+    let end = (end.floor().max(0.0) as usize).min(row_len);
+    first..end.max(first)
+}
+
+/// what the placement of the rows depends on, beside the scroll position
+#[derive(PartialEq)]
+struct LyricsLayout {
+    /// the generation of the WrappedLyrics
+    generation: usize,
+    current_y: f32,
+    distance_between_line: f32,
+    top_displayed_line: usize,
+    bottom_displayed_line: usize,
+    fade_lines: f32,
+}
+
+/// scroll the lyrics when the position in them change, then spawn the rows that became
+/// visible, despawn those that aren't anymore and place the others. Nothing is done while the
+/// lyrics are still and their layout doesn't change.
+fn move_music_text_system(
+    commands: &mut Commands,
+    time: Res<Time>,
+    mut position_reader: Local<EventReader<PositionChanged>>,
+    position_events: Res<Events<PositionChanged>>,
+    ongoing_music_setting: Res<OngoingMusicDisplaySetting>,
+    fonts: Res<Fonts>,
+    ui_materials: Res<UiMaterials>,
+    wrapped_lyrics: Res<WrappedLyrics>,
+    mut ongoing_music_data: ResMut<OngoingMusicDisplayData>,
+    mut last_layout: Local<Option<LyricsLayout>>,
+    mut queries: QuerySet<(
+        Query<(Entity, &MusicDisplayedLine, &mut Style, &mut LineOpacity)>,
+        Query<(Entity, &MusicDisplayedChords, &mut Style, &mut Text)>,
+    )>,
+) {
+    let lines = match wrapped_lyrics.lines.as_ref() {
+        Some(lines) => lines,
+        None => return,
+    };
+    let scroll = &mut ongoing_music_data.scroll;
+    for PositionChanged { line, chara } in position_reader.iter(&position_events) {
+        let row = lines.row_at(*line, *chara);
+        scroll.set_target(target_y_coordinate(row, &ongoing_music_setting));
+    }
+    if scroll.easing() != ongoing_music_setting.scroll_easing {
+        scroll.set_easing(ongoing_music_setting.scroll_easing);
+    };
+    let moving = scroll.is_moving();
+    scroll.update(time.delta_seconds());
+    let layout = LyricsLayout {
+        generation: wrapped_lyrics.generation,
+        current_y: ongoing_music_setting.current_y,
+        distance_between_line: ongoing_music_setting.distance_between_line,
+        top_displayed_line: ongoing_music_setting.top_displayed_line,
+        bottom_displayed_line: ongoing_music_setting.bottom_displayed_line,
+        fade_lines: ongoing_music_setting.fade_lines,
+    };
+    // the rows of a previous wrapping are all spawned again
+    let rewrapped =
+        last_layout.as_ref().map(|layout| layout.generation) != Some(wrapped_lyrics.generation);
+    if !moving && !rewrapped && last_layout.as_ref() == Some(&layout) {
+        return;
+    };
+    *last_layout = Some(layout);
+    let placement = |row_count| {
+        if rewrapped {
+            None
+        } else {
+            line_placement(row_count, &ongoing_music_setting, &ongoing_music_data)
+        }
+    };
+
+    let mut displayed_rows = HashSet::new();
+    for (entity, MusicDisplayedLine(row_count), mut style, mut line_opacity) in
+        queries.q0_mut().iter_mut()
+    {
+        match placement(*row_count) {
+            Some((top, opacity)) => {
+                displayed_rows.insert(*row_count);
+                style.position.top = Val::Px(top);
+                if line_opacity.0 != opacity {
+                    line_opacity.0 = opacity;
+                };
+            }
+            None => commands.despawn_recursive(entity),
+        };
+    }
+    let mut displayed_chords = HashSet::new();
+    for (entity, MusicDisplayedChords(row_count), mut style, mut text) in
+        queries.q1_mut().iter_mut()
+    {
+        match placement(*row_count) {
+            Some((top, opacity)) => {
+                displayed_chords.insert(*row_count);
+                style.position.top =
+                    Val::Px(top - ongoing_music_setting.distance_between_line / 2.0);
+                let color = faded(ongoing_music_setting.chords_color, opacity);
+                // only mutate the text when required, as a changed text is laid out again
+                if text.style.color != color {
+                    text.style.color = color;
+                };
+            }
+            None => commands.despawn_recursive(entity),
+        };
+    }
+
+    let rows = visible_rows(
+        lines.rows().len(),
+        &ongoing_music_setting,
+        &ongoing_music_data,
+    );
+    for row_count in rows {
+        let (top, opacity) =
+            match line_placement(row_count, &ongoing_music_setting, &ongoing_music_data) {
+                Some(placement) => placement,
+                None => continue,
+            };
+        // the text of the rows are spawned by update_music_line_runs_system
+        if !displayed_rows.contains(&row_count) {
+            commands
+                .spawn(NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        position: Rect {
+                            top: Val::Px(top),
+                            ..Default::default()
+                        },
+                        flex_direction: FlexDirection::Row,
+                        ..Default::default()
+                    },
+                    material: ui_materials.transparent.clone(),
+                    ..Default::default()
+                })
+                .with(MusicDisplayedLine(row_count))
+                .with(DisplayedRuns(None))
+                .with(LineOpacity(opacity))
+                .with(PlayingEntity);
+        };
+        let chords = &wrapped_lyrics.chords[row_count];
+        if !chords.is_empty() && !displayed_chords.contains(&row_count) {
+            let position = Rect {
+                top: Val::Px(top - ongoing_music_setting.distance_between_line / 2.0),
+                ..Default::default()
+            };
+            commands
+                .spawn(absolute_text(
+                    chords.clone(),
+                    position,
+                    &fonts,
+                    fonts.ongoing_music_font_size / 2.0,
+                    faded(ongoing_music_setting.chords_color, opacity),
+                ))
+                .with(MusicDisplayedChords(row_count))
+                .with(PlayingEntity);
+        };
+    }
+}
+
+/// apply the opacity of the lines to their text, when it change
+fn fade_music_lines_system(
+    line_query: Query<(&LineOpacity, &DisplayedRuns, &Children), Changed<LineOpacity>>,
+    mut text_query: Query<&mut Text>,
+) {
+    for (LineOpacity(opacity), DisplayedRuns(runs), children) in line_query.iter() {
+        let runs = match runs {
+            Some(runs) => runs,
+            None => continue,
+        };
+        for (child, (_, color, _)) in children.iter().zip(runs) {
+            let color = faded(*color, *opacity);
+            if let Ok(mut text) = text_query.get_mut(*child) {
+                // only mutate the text when required, as a changed text is laid out again
+                if text.style.color != color {
+                    text.style.color = color;
+                };
+            };
+        }
+    }
+}
+
+/// the runs of text of every visual row, with their color. The current line and the lines
+/// already typed are colored by the live alignment, with a caret at the aligned position. The
+/// other rows are a single run, and the rows that aren't displayed are empty. In the blind mode,
+/// the lyrics that are not typed yet are hidden.
+fn line_runs(
+    playing_music: &PlayingMusic,
+    lines: &WrappedLines,
+    ongoing_music_setting: &OngoingMusicDisplaySetting,
+    mode: GameMode,
+) -> Vec<Vec<(String, Color)>> {
+    let blind = mode == GameMode::Blind;
+    let mut result = vec![Vec::new(); lines.rows().len()];
+    if lines.rows().is_empty() {
+        return result;
+    };
+    let actual_line = playing_music.position_in_source_lines();
+    let actual_row = current_row(playing_music, lines);
+    // the rows that are fading in or out are displayed too
+    let fade_lines = ongoing_music_setting.fade_lines.ceil() as usize;
+    let first_row =
+        actual_row.saturating_sub(ongoing_music_setting.top_displayed_line + fade_lines);
+    let end_row = (actual_row + ongoing_music_setting.bottom_displayed_line + fade_lines + 1)
+        .min(lines.rows().len());
+    let first_line = lines.rows()[first_row].line;
+    let end_line = lines.rows()[end_row - 1].line + 1;
+    let live_diff = playing_music.live_diff(first_line, actual_line + 1);
+    let mut caret_placed = false;
+    for line_count in first_line..end_line {
+        let line = &playing_music.lines()[line_count];
+        let line_rows: Vec<Vec<(String, Color)>> = if !playing_music.is_line_typed(line_count) {
+            lines
+                .split_text(line_count, line)
+                .into_iter()
+                .map(|text| vec![(text, ongoing_music_setting.section_heading_color)])
+                .collect()
+        } else if let Some(diff_runs) = live_diff.get(line_count - first_line) {
+            let row_diffs = lines.split_runs(line_count, diff_runs);
+            let row_diffs_len = row_diffs.len();
+            let mut line_rows = Vec::new();
+            for (row_count, diff_runs) in row_diffs.into_iter().enumerate() {
+                let mut runs = Vec::new();
+                for DiffRun { status, text } in diff_runs {
+                    if status == Some(AlignmentStatus::Untyped) && !caret_placed {
+                        runs.push(("|".to_string(), ongoing_music_setting.caret_color));
+                        caret_placed = true;
+                    };
+                    if blind && status == Some(AlignmentStatus::Untyped) {
+                        continue;
+                    };
+                    let color =
+                        if status == Some(AlignmentStatus::Untyped) && line_count == actual_line {
+                            ongoing_music_setting.current_color
+                        } else {
+                            ongoing_music_setting.status_color(status)
+                        };
+                    runs.push((text, color));
+                }
+                if line_count == actual_line && row_count + 1 == row_diffs_len && !caret_placed {
+                    runs.push(("|".to_string(), ongoing_music_setting.caret_color));
+                    caret_placed = true;
+                };
+                line_rows.push(runs);
+            }
+            line_rows
+        } else if blind {
+            lines.rows_of_line(line_count).map(|_| Vec::new()).collect()
+        } else {
+            lines
+                .split_text(line_count, line)
+                .into_iter()
+                .map(|text| vec![(text, ongoing_music_setting.non_current_color)])
+                .collect()
+        };
+        for (row_count, runs) in lines.rows_of_line(line_count).zip(line_rows) {
+            if row_count >= first_row && row_count < end_row {
+                result[row_count] = runs;
+            };
+        }
+    }
+    result
+}
+
+/// respawn the text of the rows whose runs changed. The runs are only computed again after a
+/// keystroke, when a row was never displayed, or when the fonts of the lyrics are loaded.
+fn update_music_line_runs_system(
+    commands: &mut Commands,
+    ongoing_music: Res<OngoingMusic>,
+    ongoing_music_setting: Res<OngoingMusicDisplaySetting>,
+    wrapped_lyrics: Res<WrappedLyrics>,
+    fonts: Res<Fonts>,
+    font_assets: Res<Assets<Font>>,
+    settings: Res<Settings>,
+    mut last_keystroke_count: Local<Option<usize>>,
+    mut fonts_were_loaded: Local<bool>,
+    mut query: Query<(
+        Entity,
+        &MusicDisplayedLine,
+        &mut DisplayedRuns,
+        &LineOpacity,
+        Option<&Children>,
+    )>,
+) {
+    let (playing_music, lines) = match (ongoing_music.0.as_ref(), wrapped_lyrics.lines.as_ref()) {
+        (Some(playing_music), Some(lines)) => (playing_music, lines),
+        _ => return,
+    };
+    let keystroke_count = playing_music.keystrokes().len();
+    let never_displayed = query.iter_mut().any(|(_, _, runs, _, _)| runs.0.is_none());
+    let fonts_loaded = fonts.lyrics_fonts_loaded(&font_assets);
+    if *last_keystroke_count == Some(keystroke_count)
+        && !never_displayed
+        && *fonts_were_loaded == fonts_loaded
+    {
+        return;
+    };
+    *last_keystroke_count = Some(keystroke_count);
+    *fonts_were_loaded = fonts_loaded;
+    let rows = line_runs(playing_music, lines, &ongoing_music_setting, settings.mode);
+    for (
+        entity,
+        MusicDisplayedLine(row_count),
+        mut displayed_runs,
+        LineOpacity(opacity),
+        children,
+    ) in query.iter_mut()
+    {
+        let runs = match rows.get(*row_count) {
+            Some(runs) => font_sections(runs, &fonts, &font_assets),
+            None => continue,
+        };
+        if displayed_runs.0.as_ref() == Some(&runs) {
+            continue;
+        };
+        if let Some(children) = children {
+            for child in children.iter() {
+                commands.despawn_recursive(*child);
+            }
+        };
+        commands.set_current_entity(entity);
+        commands.with_children(|parent| {
+            for (value, color, font) in &runs {
+                parent.spawn(lyrics_text(
+                    value.clone(),
+                    *font,
+                    &fonts,
+                    fonts.ongoing_music_font_size,
+                    faded(*color, *opacity),
+                ));
+            }
+        });
+        displayed_runs.0 = Some(runs);
+    }
+}
+
+/// apply the changed settings to the typing view, the lyrics being wrapped and colored again
+fn apply_display_settings_system(
+    settings: ChangedRes<Settings>,
+    systems: Res<MusicTyperSystems>,
+    mut ongoing_music_setting: ResMut<OngoingMusicDisplaySetting>,
+    mut wrapped_lyrics: ResMut<WrappedLyrics>,
+    mut query: Query<&mut DisplayedRuns>,
+) {
+    ongoing_music_setting.apply_settings(&settings, systems.window_layout);
+    wrapped_lyrics.lines = None;
+    for mut displayed_runs in query.iter_mut() {
+        displayed_runs.0 = None;
+    }
+}
+
+/// color the typing view with the changed theme. The lines are colored again by
+/// update_music_line_runs_system.
+fn apply_display_theme_system(
+    theme: ChangedRes<Theme>,
+    mut ongoing_music_setting: ResMut<OngoingMusicDisplaySetting>,
+    mut queries: QuerySet<(
+        Query<&mut DisplayedRuns>,
+        Query<&mut Text, With<PlayingHeader>>,
+        Query<&mut Text, With<OngoingMusicStatsMarker>>,
+        Query<&mut Text, With<GhostDisplay>>,
+    )>,
+) {
+    ongoing_music_setting.apply_theme(&theme);
+    for mut displayed_runs in queries.q0_mut().iter_mut() {
+        displayed_runs.0 = None;
+    }
+    for mut text in queries.q1_mut().iter_mut() {
+        text.style.color = ongoing_music_setting.non_current_color;
+    }
+    for mut text in queries.q2_mut().iter_mut() {
+        text.style.color = ongoing_music_setting.non_current_color;
+    }
+    for mut text in queries.q3_mut().iter_mut() {
+        text.style.color = ongoing_music_setting.ghost_color;
+    }
+}
+
+fn update_stats_text(
+    time: Res<Time>,
+    mut timer: Local<StatsUpdateTimer>,
+    ongoing_music: Res<OngoingMusic>,
+    mut query: Query<&mut Text, With<OngoingMusicStatsMarker>>,
+) {
+    timer.0.tick(time.delta_seconds());
+    if !timer.0.finished() {
+        return;
+    };
+    if let Some(ongoing_music) = &ongoing_music.0 {
+        let stats = ongoing_music.stats();
+        for mut text in query.iter_mut() {
+            text.value = format!(
+                "{:.0} wpm  {:.0}% accuracy  {:.0}% correctness",
+                stats.net_wpm,
+                stats.corrected_accuracy * 100.0,
+                ongoing_music.correctness() * 100.0
+            );
+        }
+    }
+}
@@ -0,0 +1,393 @@
+use super::playing::OngoingMusicDisplaySetting;
+use super::APP_STATE_STAGE;
+use super::{absolute_text, color, despawn_all, text, AppState, Fonts, UiMaterials};
+use super::{LastResult, OngoingGhost, OngoingMusic, OngoingReplay, OngoingSong, Scores};
+use crate::{DiffRun, Ghost, PlayingMusic, Replay, ReplayPlayer, Theme};
+use bevy::prelude::*;
+use log::error;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// the number of lines of the lyric diff displayed at once
+const DIFF_LINE_COUNT: usize = 12;
+
+pub fn build(app: &mut AppBuilder) {
+    app.add_resource(ResultsDiff::default())
+        .add_event::<ResultsAction>()
+        .on_state_enter(APP_STATE_STAGE, AppState::Results, spawn_results.system())
+        .on_state_update(
+            APP_STATE_STAGE,
+            AppState::Results,
+            results_key_system.system(),
+        )
+        .on_state_update(
+            APP_STATE_STAGE,
+            AppState::Results,
+            results_button_system.system(),
+        )
+        .on_state_update(
+            APP_STATE_STAGE,
+            AppState::Results,
+            results_action_system.system(),
+        )
+        .on_state_update(
+            APP_STATE_STAGE,
+            AppState::Results,
+            update_diff_view_system.system(),
+        )
+        .on_state_exit(
+            APP_STATE_STAGE,
+            AppState::Results,
+            despawn_all::<ResultsEntity>.system(),
+        );
+}
+
+/// an entity of the results screen
+struct ResultsEntity;
+
+/// the node that contains the displayed lines of the lyric diff
+struct DiffView;
+
+/// the text that tell whether the last action succeeded
+struct ResultsStatusText;
+
+/// the lyric diff of the finished song, and the first of its line that is displayed
+#[derive(Default)]
+struct ResultsDiff {
+    lines: Vec<Vec<DiffRun>>,
+    first_line: usize,
+}
+
+impl ResultsDiff {
+    fn scroll(&mut self, lines: isize) {
+        let last_first_line = self.lines.len().saturating_sub(DIFF_LINE_COUNT);
+        let first_line = (self.first_line as isize + lines).max(0) as usize;
+        self.first_line = first_line.min(last_first_line);
+    }
+}
+
+/// what can be done from the results screen, with a key or a button
+#[derive(Clone, Copy, PartialEq)]
+enum ResultsAction {
+    Retry,
+    SaveReplay,
+    SongSelect,
+    MainMenu,
+    /// replay the run that was just finished
+    Replay,
+    /// restart the song, racing against the personal best
+    Ghost,
+}
+
+fn spawn_results(
+    commands: &mut Commands,
+    fonts: Res<Fonts>,
+    theme: Res<Theme>,
+    ui_materials: Res<UiMaterials>,
+    last_result: Res<LastResult>,
+    ongoing_music: Res<OngoingMusic>,
+    ongoing_music_setting: Res<OngoingMusicDisplaySetting>,
+    mut diff: ResMut<ResultsDiff>,
+) {
+    let font_size = fonts.ongoing_music_font_size / 2.0;
+    let mut lines = vec!["Results".to_string()];
+    if let Some(result) = last_result.0.as_ref() {
+        lines.push(format!(
+            "correctness: {:.1}%  distance: {}  time: {:.1} s",
+            result.correctness * 100.0,
+            result.distance,
+            result.duration.as_secs_f64()
+        ));
+    };
+    if let Some(music) = ongoing_music.0.as_ref() {
+        let stats = music.stats();
+        lines.push(format!(
+            "speed: {:.0} wpm  accuracy: {:.1}%",
+            stats.net_wpm,
+            stats.corrected_accuracy * 100.0
+        ));
+    };
+    lines.push(format!(
+        "Up/Down: scroll  R: retry  S: save the replay  {:?}: replay  {:?}: race your best run",
+        ongoing_music_setting.replay_key, ongoing_music_setting.ghost_key
+    ));
+    for (line_count, line) in lines.into_iter().enumerate() {
+        let position = Rect {
+            top: Val::Px(20.0 + line_count as f32 * font_size * 1.5),
+            left: Val::Px(50.0),
+            ..Default::default()
+        };
+        commands
+            .spawn(absolute_text(
+                line,
+                position,
+                &fonts,
+                font_size,
+                color(theme.text),
+            ))
+            .with(ResultsEntity);
+    }
+    let position = Rect {
+        bottom: Val::Px(100.0),
+        left: Val::Px(50.0),
+        ..Default::default()
+    };
+    commands
+        .spawn(absolute_text(
+            String::new(),
+            position,
+            &fonts,
+            font_size,
+            color(theme.dim_text),
+        ))
+        .with(ResultsStatusText)
+        .with(ResultsEntity);
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(20.0),
+                    left: Val::Px(50.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            material: ui_materials.transparent.clone(),
+            ..Default::default()
+        })
+        .with(ResultsEntity)
+        .with_children(|parent| {
+            for (label, action) in &[
+                ("Retry", ResultsAction::Retry),
+                ("Save the replay", ResultsAction::SaveReplay),
+                ("Choose another song", ResultsAction::SongSelect),
+            ] {
+                parent
+                    .spawn(ButtonBundle {
+                        style: Style {
+                            margin: Rect::all(Val::Px(10.0)),
+                            padding: Rect::all(Val::Px(10.0)),
+                            ..Default::default()
+                        },
+                        material: ui_materials.button.clone(),
+                        ..Default::default()
+                    })
+                    .with(*action)
+                    .with_children(|parent| {
+                        let label = label.to_string();
+                        parent.spawn(text(label, &fonts, font_size, color(theme.text)));
+                    });
+            }
+        });
+
+    // the diff is derived from the final state of the music
+    diff.lines = ongoing_music
+        .0
+        .as_ref()
+        .map(|music| music.source_diff(music.is_finished()))
+        .unwrap_or_default();
+    diff.first_line = 0;
+}
+
+fn results_key_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    ongoing_music_setting: Res<OngoingMusicDisplaySetting>,
+    mut diff: ResMut<ResultsDiff>,
+    mut actions: ResMut<Events<ResultsAction>>,
+) {
+    for (key, action) in &[
+        (KeyCode::Return, ResultsAction::SongSelect),
+        (KeyCode::R, ResultsAction::Retry),
+        (KeyCode::S, ResultsAction::SaveReplay),
+        (KeyCode::Escape, ResultsAction::MainMenu),
+        (ongoing_music_setting.replay_key, ResultsAction::Replay),
+        (ongoing_music_setting.ghost_key, ResultsAction::Ghost),
+    ] {
+        if keyboard_input.just_pressed(*key) {
+            actions.send(*action);
+        };
+    }
+    for (key, lines) in &[
+        (KeyCode::Up, -1),
+        (KeyCode::Down, 1),
+        (KeyCode::PageUp, -(DIFF_LINE_COUNT as isize)),
+        (KeyCode::PageDown, DIFF_LINE_COUNT as isize),
+    ] {
+        if keyboard_input.just_pressed(*key) {
+            diff.scroll(*lines);
+        };
+    }
+}
+
+fn results_button_system(
+    ui_materials: Res<UiMaterials>,
+    mut actions: ResMut<Events<ResultsAction>>,
+    mut query: Query<
+        (&Interaction, &ResultsAction, &mut Handle<ColorMaterial>),
+        (Mutated<Interaction>, With<Button>),
+    >,
+) {
+    for (interaction, action, mut material) in query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                actions.send(*action);
+            }
+            Interaction::Hovered => *material = ui_materials.button_hovered.clone(),
+            Interaction::None => *material = ui_materials.button.clone(),
+        };
+    }
+}
+
+/// save a replay of the run in the default replay directory
+fn save_replay(music: &PlayingMusic, song_id: String) -> io::Result<PathBuf> {
+    let directory = Replay::default_directory()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "there is no data directory"))?;
+    fs::create_dir_all(&directory)?;
+    let date = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    let path = directory.join(format!("{:016x}-{}.json", music.song_hash(), date));
+    Replay::from_playing_music(music, song_id).save(&path)?;
+    Ok(path)
+}
+
+fn results_action_system(
+    mut event_reader: Local<EventReader<ResultsAction>>,
+    actions: Res<Events<ResultsAction>>,
+    ongoing_song: Res<OngoingSong>,
+    scores: Res<Scores>,
+    mut ongoing_music: ResMut<OngoingMusic>,
+    mut ongoing_replay: ResMut<OngoingReplay>,
+    mut ongoing_ghost: ResMut<OngoingGhost>,
+    mut state: ResMut<State<AppState>>,
+    mut query: Query<&mut Text, With<ResultsStatusText>>,
+) {
+    for action in event_reader.iter(&actions) {
+        let music = match ongoing_music.0.as_mut() {
+            Some(music) => music,
+            None => {
+                state.set_next(AppState::SongSelect).unwrap();
+                return;
+            }
+        };
+        match action {
+            ResultsAction::Retry => {
+                ongoing_ghost.0 = None;
+                state.set_next(AppState::Countdown).unwrap();
+            }
+            ResultsAction::SaveReplay => {
+                let status = match save_replay(music, ongoing_song.song_id()) {
+                    Ok(path) => format!("replay saved in {}", path.display()),
+                    Err(err) => {
+                        error!("can't save the replay: {}", err);
+                        format!("can't save the replay: {}", err)
+                    }
+                };
+                for mut text in query.iter_mut() {
+                    text.value = status.clone();
+                }
+            }
+            ResultsAction::SongSelect => state.set_next(AppState::SongSelect).unwrap(),
+            ResultsAction::MainMenu => state.set_next(AppState::MainMenu).unwrap(),
+            ResultsAction::Replay => {
+                let replay = Replay::from_playing_music(music, ongoing_song.song_id());
+                let mut player = ReplayPlayer::new(replay);
+                player.prepare(music);
+                ongoing_replay.0 = Some(player);
+                state.set_next(AppState::Playing).unwrap();
+            }
+            ResultsAction::Ghost => {
+                // race against the personal best, or the run that was just finished if there is
+                // no personal best
+                let best_replay = scores.0.as_ref().and_then(|database| {
+                    let replay = match database.personal_best(music.song_hash()) {
+                        Ok(Some(record)) => database.replay(&record),
+                        Ok(None) => Ok(None),
+                        Err(err) => Err(err),
+                    };
+                    match replay {
+                        Ok(replay) => replay,
+                        Err(err) => {
+                            error!("can't read the personal best: {}", err);
+                            None
+                        }
+                    }
+                });
+                let replay =
+                    best_replay.unwrap_or_else(|| Replay::from_playing_music(music, String::new()));
+                music.restart();
+                ongoing_ghost.0 = Ghost::new(replay, music);
+                state.set_next(AppState::Countdown).unwrap();
+            }
+        };
+        // the other actions leave the results screen
+        if *action != ResultsAction::SaveReplay {
+            return;
+        };
+    }
+}
+
+/// display the lines of the lyric diff that are scrolled to, each run of characters having the
+/// color of its alignment status
+fn update_diff_view_system(
+    commands: &mut Commands,
+    diff: ChangedRes<ResultsDiff>,
+    fonts: Res<Fonts>,
+    ui_materials: Res<UiMaterials>,
+    ongoing_music_setting: Res<OngoingMusicDisplaySetting>,
+    query: Query<Entity, With<DiffView>>,
+) {
+    despawn_all::<DiffView>(commands, query);
+    let font_size = fonts.ongoing_music_font_size / 2.0;
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(20.0 + font_size * 1.5 * 5.0),
+                    left: Val::Px(50.0),
+                    ..Default::default()
+                },
+                flex_direction: FlexDirection::ColumnReverse,
+                ..Default::default()
+            },
+            material: ui_materials.transparent.clone(),
+            ..Default::default()
+        })
+        .with(DiffView)
+        .with(ResultsEntity)
+        .with_children(|parent| {
+            for line in diff
+                .lines
+                .iter()
+                .skip(diff.first_line)
+                .take(DIFF_LINE_COUNT)
+            {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Row,
+                            size: Size::new(Val::Auto, Val::Px(font_size * 1.2)),
+                            ..Default::default()
+                        },
+                        material: ui_materials.transparent.clone(),
+                        ..Default::default()
+                    })
+                    .with_children(|parent| {
+                        for run in line {
+                            parent.spawn(text(
+                                run.text.clone(),
+                                &fonts,
+                                font_size,
+                                ongoing_music_setting.status_color(run.status),
+                            ));
+                        }
+                    });
+            }
+        });
+}
@@ -0,0 +1,200 @@
+use super::APP_STATE_STAGE;
+use super::{absolute_text, color, despawn_all, AppState, Fonts, SettingsFile, UiMaterials};
+use crate::{Settings, SettingsItem, Theme};
+use bevy::prelude::*;
+use log::error;
+
+pub fn build(app: &mut AppBuilder) {
+    app.add_resource(SettingsMenu {
+        selected: 0,
+        return_state: AppState::MainMenu,
+        status: String::new(),
+    })
+    .on_state_enter(
+        APP_STATE_STAGE,
+        AppState::Settings,
+        spawn_settings_menu.system(),
+    )
+    .on_state_update(
+        APP_STATE_STAGE,
+        AppState::Settings,
+        settings_menu_key_system.system(),
+    )
+    .on_state_update(
+        APP_STATE_STAGE,
+        AppState::Settings,
+        update_settings_menu_system.system(),
+    )
+    .on_state_exit(
+        APP_STATE_STAGE,
+        AppState::Settings,
+        despawn_all::<SettingsMenuEntity>.system(),
+    );
+}
+
+/// the highlighted item of the settings menu
+pub struct SettingsMenu {
+    /// the index of the item in SettingsItem::ALL
+    selected: usize,
+    /// the state the menu was opened from
+    return_state: AppState,
+    /// where the settings were saved, or why they weren't
+    status: String,
+}
+
+/// an entity of the settings menu
+struct SettingsMenuEntity;
+
+/// the texts of the settings menu that are updated when the settings change
+#[derive(Clone, Copy, PartialEq)]
+enum SettingsMenuText {
+    /// the title and the help
+    Heading,
+    /// the name and the value of an item of SettingsItem::ALL
+    Item(usize),
+    Status,
+}
+
+fn spawn_settings_menu(
+    commands: &mut Commands,
+    fonts: Res<Fonts>,
+    theme: Res<Theme>,
+    ui_materials: Res<UiMaterials>,
+    state: Res<State<AppState>>,
+    mut menu: ResMut<SettingsMenu>,
+) {
+    menu.return_state = state.previous().copied().unwrap_or(AppState::MainMenu);
+    menu.status.clear();
+    // the lyrics of a paused song stay behind the menu
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                ..Default::default()
+            },
+            material: ui_materials.background.clone(),
+            ..Default::default()
+        })
+        .with(SettingsMenuEntity);
+    let font_size = fonts.ongoing_music_font_size / 2.0;
+    let help = "Up/Down: choose  Left/Right: change  Escape: back";
+    for (value, top) in &[("Settings", 50.0), (help, 100.0)] {
+        let position = Rect {
+            top: Val::Px(*top),
+            left: Val::Px(100.0),
+            ..Default::default()
+        };
+        commands
+            .spawn(absolute_text(
+                value.to_string(),
+                position,
+                &fonts,
+                font_size,
+                color(theme.dim_text),
+            ))
+            .with(SettingsMenuText::Heading)
+            .with(SettingsMenuEntity);
+    }
+    let mut texts: Vec<SettingsMenuText> = (0..SettingsItem::ALL.len())
+        .map(SettingsMenuText::Item)
+        .collect();
+    texts.push(SettingsMenuText::Status);
+    for (row, text) in texts.into_iter().enumerate() {
+        let position = Rect {
+            top: Val::Px(200.0 + row as f32 * font_size * 1.5),
+            left: Val::Px(100.0),
+            ..Default::default()
+        };
+        commands
+            .spawn(absolute_text(
+                String::new(),
+                position,
+                &fonts,
+                font_size,
+                color(theme.text),
+            ))
+            .with(text)
+            .with(SettingsMenuEntity);
+    }
+}
+
+/// change the highlighted setting, and save the settings after every change. The changes are
+/// applied by the systems that watch the Settings resource, the theme included. The settings
+/// overridden for this run are saved with their previous value, unless the player changed them.
+fn settings_menu_key_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut settings_file: ResMut<SettingsFile>,
+    mut menu: ResMut<SettingsMenu>,
+    mut settings: ResMut<Settings>,
+    mut state: ResMut<State<AppState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        state.set_next(menu.return_state).unwrap();
+        return;
+    };
+    let item_count = SettingsItem::ALL.len();
+    if keyboard_input.just_pressed(KeyCode::Up) {
+        menu.selected = (menu.selected + item_count - 1) % item_count;
+    };
+    if keyboard_input.just_pressed(KeyCode::Down) {
+        menu.selected = (menu.selected + 1) % item_count;
+    };
+    let up = if keyboard_input.just_pressed(KeyCode::Right)
+        || keyboard_input.just_pressed(KeyCode::Return)
+    {
+        true
+    } else if keyboard_input.just_pressed(KeyCode::Left) {
+        false
+    } else {
+        return;
+    };
+    let item = SettingsItem::ALL[menu.selected];
+    item.adjust(&mut settings, up);
+    settings_file.overrides.forget(item);
+    let saved = settings_file
+        .overrides
+        .unapply(&settings, &settings_file.saved);
+    menu.status = match &settings_file.path {
+        Some(path) => match saved.save(path) {
+            Ok(()) => format!("saved in {}", path.display()),
+            Err(err) => {
+                error!("can't save the settings in {:?}: {}", path, err);
+                format!("can't save the settings: {}", err)
+            }
+        },
+        None => "the changes are not saved".into(),
+    };
+    settings_file.saved = saved;
+}
+
+/// display the settings, in the colors of the theme that may just have been changed
+fn update_settings_menu_system(
+    menu: Res<SettingsMenu>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+    mut query: Query<(&SettingsMenuText, &mut Text)>,
+) {
+    for (settings_menu_text, mut text) in query.iter_mut() {
+        match settings_menu_text {
+            SettingsMenuText::Heading => text.style.color = color(theme.dim_text),
+            SettingsMenuText::Item(index) => {
+                let item = SettingsItem::ALL[*index];
+                let selected = *index == menu.selected;
+                let label = format!("{}: {}", item.name(), item.value_text(&settings));
+                text.value = theme.menu_label(&label, selected);
+                text.style.color = color(if selected {
+                    theme.highlight
+                } else {
+                    theme.text
+                });
+            }
+            SettingsMenuText::Status => {
+                if text.value != menu.status {
+                    text.value = menu.status.clone();
+                };
+                text.style.color = color(theme.text);
+            }
+        };
+    }
+}
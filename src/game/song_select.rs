@@ -0,0 +1,432 @@
+use super::{absolute_text, color, despawn_all, AppState, Fonts, APP_STATE_STAGE};
+use super::{OngoingGhost, OngoingMusic, OngoingReplay, OngoingSong, Scores};
+use crate::{ChordProSong, LibraryEntry, LibrarySearch, SessionResult, Settings, SongLibrary};
+use crate::{SongSort, Theme};
+use bevy::{prelude::*, window::ReceivedCharacter};
+use log::error;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+static TEST_SONG: &str = r#"{title: Light of Your Cutie Mark}
+{artist: My Little Pony}
+
+Cutie Mark Crusaders, get out of my way
+Those ponies need to know the truth
+And they’ll hear it from me
+
+Stop! Diamond Tiara, this is not the way
+You know you’re better than this hostility
+
+You don’t even know me at all
+Don’t understand the meaning of my fall
+What my family would think if I ever
+Fail at anything
+
+I’m a diamond – that means you’ll never break
+No matter what be the cost of the path I take
+Whatever I have to do to win in the end
+
+Stop! This is not the answer
+Wait! And it’s plainly seen
+Listen! You can redeem yourself
+But by helping others, not by being mean
+
+We know you want friends who admire you
+You want to be the star with all the power too
+But there’s a better way, there’s a better wa-a-ay
+
+There’s so much more still left to
+Learn about yourself
+See the light that shines in you
+We know you can be somepony else
+
+You can stop right now
+And try another start
+You’ll finally free yourself from the dark
+And see the light
+And see the light of your cutie mark
+"#;
+
+/// the number of songs displayed in the list at once
+const DISPLAYED_SONG_COUNT: usize = 12;
+/// the number of lyric lines shown in the preview
+const PREVIEW_LINE_COUNT: usize = 8;
+
+pub fn build(app: &mut AppBuilder, library_directory: Option<PathBuf>) {
+    app.add_resource(SongSelection::new(load_library(library_directory)))
+        .on_state_enter(
+            APP_STATE_STAGE,
+            AppState::SongSelect,
+            spawn_song_select.system(),
+        )
+        .on_state_update(
+            APP_STATE_STAGE,
+            AppState::SongSelect,
+            song_search_system.system(),
+        )
+        .on_state_update(
+            APP_STATE_STAGE,
+            AppState::SongSelect,
+            song_select_key_system.system(),
+        )
+        .on_state_update(
+            APP_STATE_STAGE,
+            AppState::SongSelect,
+            update_song_list_system.system(),
+        )
+        .on_state_exit(
+            APP_STATE_STAGE,
+            AppState::SongSelect,
+            despawn_all::<SongSelectEntity>.system(),
+        );
+}
+
+/// the songs of the given library directory or of the default one, or the built in song if
+/// there is none
+fn load_library(directory: Option<PathBuf>) -> SongLibrary {
+    let mut library = match directory.or_else(SongLibrary::default_directory) {
+        Some(directory) => SongLibrary::load(&directory).unwrap_or_else(|err| {
+            error!("can't read the song library {:?}: {}", directory, err);
+            SongLibrary::default()
+        }),
+        None => SongLibrary::default(),
+    };
+    if library.entries.is_empty() {
+        library.entries.push(LibraryEntry::from_chordpro(
+            ChordProSong::parse(TEST_SONG),
+            None,
+        ));
+    };
+    library
+}
+
+/// the songs that can be chosen, and the one that is highlighted
+pub struct SongSelection {
+    library: SongLibrary,
+    search: LibrarySearch,
+    sort: SongSort,
+    /// the best run of the songs that were played, by song hash
+    personal_bests: HashMap<u64, SessionResult>,
+    /// the index of the entries that are listed, in order
+    displayed: Vec<usize>,
+    /// the index in displayed of the highlighted entry
+    selected: usize,
+}
+
+impl SongSelection {
+    fn new(library: SongLibrary) -> Self {
+        let mut selection = Self {
+            search: LibrarySearch::new(&library),
+            library,
+            sort: SongSort::Title,
+            personal_bests: HashMap::new(),
+            displayed: Vec::new(),
+            selected: 0,
+        };
+        selection.update_displayed();
+        selection
+    }
+
+    /// sort and filter the entries again, keeping the highlighted entry if it is still listed
+    fn update_displayed(&mut self) {
+        let selected_entry = self.selected_entry_index();
+        let personal_bests = &self.personal_bests;
+        let order = self.library.sorted(self.sort, |song_hash| {
+            personal_bests
+                .get(&song_hash)
+                .map(|result| result.correctness)
+        });
+        self.displayed = self.search.filter(&order);
+        self.selected = selected_entry
+            .and_then(|entry| self.displayed.iter().position(|index| *index == entry))
+            .unwrap_or(0);
+    }
+
+    fn selected_entry_index(&self) -> Option<usize> {
+        self.displayed.get(self.selected).copied()
+    }
+
+    fn selected_entry(&self) -> Option<&LibraryEntry> {
+        self.selected_entry_index()
+            .map(|entry| &self.library.entries[entry])
+    }
+
+    /// the first row of displayed that is visible, so that the highlighted one is visible
+    fn first_visible_row(&self) -> usize {
+        (self.selected + 1).saturating_sub(DISPLAYED_SONG_COUNT)
+    }
+
+    fn read_personal_bests(&mut self, scores: &Scores) {
+        self.personal_bests.clear();
+        if let Some(database) = scores.0.as_ref() {
+            for entry in &self.library.entries {
+                match database.personal_best(entry.song_hash) {
+                    Ok(Some(record)) => {
+                        self.personal_bests.insert(entry.song_hash, record.result);
+                    }
+                    Ok(None) => (),
+                    Err(err) => {
+                        error!("can't read the personal best: {}", err);
+                        return;
+                    }
+                };
+            }
+        };
+    }
+}
+
+/// an entity of the song selection screen
+struct SongSelectEntity;
+
+/// the texts of the song selection screen that are updated when the selection change
+#[derive(Clone, Copy, PartialEq)]
+enum SongSelectText {
+    /// a row of the song list
+    Row(usize),
+    Search,
+    Sort,
+    /// the first lines of the highlighted song
+    Preview,
+    /// the statistics of the highlighted song
+    Stats,
+}
+
+fn song_label(entry: &LibraryEntry) -> String {
+    match (entry.title(), entry.artist()) {
+        (Some(title), Some(artist)) => format!("{} - {}", title, artist),
+        (Some(title), None) => title.to_string(),
+        (None, Some(artist)) => artist.to_string(),
+        (None, None) => "untitled".to_string(),
+    }
+}
+
+fn sort_name(sort: SongSort) -> &'static str {
+    match sort {
+        SongSort::Title => "title",
+        SongSort::Artist => "artist",
+        SongSort::Difficulty => "difficulty",
+        SongSort::PersonalBest => "personal best",
+    }
+}
+
+fn preview_text(entry: &LibraryEntry) -> String {
+    entry
+        .lyrics
+        .lines
+        .iter()
+        .filter(|line| !line.text.trim().is_empty())
+        .take(PREVIEW_LINE_COUNT)
+        .map(|line| line.text.as_str())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn stats_text(entry: &LibraryEntry, personal_best: Option<&SessionResult>) -> String {
+    let typed_lines = entry.lyrics.lines.iter().filter(|line| line.typed);
+    let char_count: usize = typed_lines
+        .clone()
+        .map(|line| line.text.chars().count())
+        .sum();
+    let mut stats = format!(
+        "{} lines  {} characters  difficulty {:.0}",
+        typed_lines.count(),
+        char_count,
+        entry.difficulty()
+    );
+    match personal_best {
+        Some(result) => stats.push_str(&format!(
+            "\nbest: {:.1}% in {:.0} s",
+            result.correctness * 100.0,
+            result.duration.as_secs_f64()
+        )),
+        None => stats.push_str("\nnever played"),
+    };
+    stats
+}
+
+fn spawn_song_select(
+    commands: &mut Commands,
+    fonts: Res<Fonts>,
+    theme: Res<Theme>,
+    scores: Res<Scores>,
+    mut selection: ResMut<SongSelection>,
+) {
+    selection.read_personal_bests(&scores);
+    selection.update_displayed();
+    let font_size = fonts.ongoing_music_font_size / 2.0;
+    let help = "Up/Down: choose  Tab: sort  type to search  Enter: play  Escape: back";
+    let position = Rect {
+        bottom: Val::Px(10.0),
+        left: Val::Px(10.0),
+        ..Default::default()
+    };
+    commands
+        .spawn(absolute_text(
+            help.to_string(),
+            position,
+            &fonts,
+            font_size,
+            color(theme.dim_text),
+        ))
+        .with(SongSelectEntity);
+    let mut texts = vec![
+        (SongSelectText::Search, 10.0, 10.0, theme.emphasis),
+        (
+            SongSelectText::Sort,
+            10.0 + font_size * 1.5,
+            10.0,
+            theme.dim_text,
+        ),
+        (SongSelectText::Preview, 100.0, 900.0, theme.text),
+        (
+            SongSelectText::Stats,
+            100.0 + font_size * 1.5 * 9.0,
+            900.0,
+            theme.dim_text,
+        ),
+    ];
+    for row in 0..DISPLAYED_SONG_COUNT {
+        texts.push((
+            SongSelectText::Row(row),
+            100.0 + row as f32 * font_size * 1.5,
+            50.0,
+            theme.text,
+        ));
+    }
+    for (text, top, left, text_color) in texts {
+        let position = Rect {
+            top: Val::Px(top),
+            left: Val::Px(left),
+            ..Default::default()
+        };
+        commands
+            .spawn(absolute_text(
+                String::new(),
+                position,
+                &fonts,
+                font_size,
+                color(text_color),
+            ))
+            .with(text)
+            .with(SongSelectEntity);
+    }
+}
+
+/// type the search query
+fn song_search_system(
+    mut event_reader: Local<EventReader<ReceivedCharacter>>,
+    char_input_events: Res<Events<ReceivedCharacter>>,
+    mut selection: ResMut<SongSelection>,
+) {
+    let mut changed = false;
+    for event in event_reader.iter(&char_input_events) {
+        if event.char == '\u{8}' {
+            selection.search.pop();
+        } else if !event.char.is_control() {
+            selection.search.push(event.char);
+        } else {
+            continue;
+        };
+        changed = true;
+    }
+    if changed {
+        selection.update_displayed();
+        selection.selected = 0;
+    };
+}
+
+fn song_select_key_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    settings: Res<Settings>,
+    mut selection: ResMut<SongSelection>,
+    mut state: ResMut<State<AppState>>,
+    mut ongoing_music: ResMut<OngoingMusic>,
+    mut ongoing_song: ResMut<OngoingSong>,
+    mut ongoing_replay: ResMut<OngoingReplay>,
+    mut ongoing_ghost: ResMut<OngoingGhost>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        if selection.search.query().is_empty() {
+            state.set_next(AppState::MainMenu).unwrap();
+        } else {
+            selection.search.clear();
+            selection.update_displayed();
+        };
+        return;
+    };
+    if keyboard_input.just_pressed(KeyCode::Tab) {
+        selection.sort = selection.sort.next();
+        selection.update_displayed();
+    };
+    let song_count = selection.displayed.len();
+    if song_count == 0 {
+        return;
+    };
+    if keyboard_input.just_pressed(KeyCode::Up) {
+        selection.selected = (selection.selected + song_count - 1) % song_count;
+    };
+    if keyboard_input.just_pressed(KeyCode::Down) {
+        selection.selected = (selection.selected + 1) % song_count;
+    };
+    if keyboard_input.just_pressed(KeyCode::Return) {
+        if let Some(entry) = selection.selected_entry() {
+            let mut music = entry.to_playing_music();
+            music.set_precision(settings.precision);
+            ongoing_music.0 = Some(music);
+            ongoing_song.0 = Some(entry.song.clone());
+            ongoing_replay.0 = None;
+            ongoing_ghost.0 = None;
+            state.set_next(AppState::Countdown).unwrap();
+        };
+    };
+}
+
+fn update_song_list_system(
+    selection: ChangedRes<SongSelection>,
+    theme: Res<Theme>,
+    mut query: Query<(&SongSelectText, &mut Text)>,
+) {
+    let selected_entry = selection.selected_entry();
+    for (song_select_text, mut text) in query.iter_mut() {
+        match song_select_text {
+            SongSelectText::Row(row) => {
+                let displayed_row = selection.first_visible_row() + row;
+                let selected = displayed_row == selection.selected;
+                text.value = selection
+                    .displayed
+                    .get(displayed_row)
+                    .map(|entry| {
+                        theme.menu_label(&song_label(&selection.library.entries[*entry]), selected)
+                    })
+                    .unwrap_or_default();
+                text.style.color = color(if selected {
+                    theme.highlight
+                } else {
+                    theme.text
+                });
+            }
+            SongSelectText::Search => {
+                text.value = if selection.search.query().is_empty() {
+                    String::new()
+                } else {
+                    format!("search: {}", selection.search.query())
+                };
+            }
+            SongSelectText::Sort => {
+                text.value = format!(
+                    "sorted by {}  {} songs",
+                    sort_name(selection.sort),
+                    selection.displayed.len()
+                );
+            }
+            SongSelectText::Preview => {
+                text.value = selected_entry.map(preview_text).unwrap_or_default();
+            }
+            SongSelectText::Stats => {
+                text.value = selected_entry
+                    .map(|entry| stats_text(entry, selection.personal_bests.get(&entry.song_hash)))
+                    .unwrap_or_default();
+            }
+        };
+    }
+}
@@ -0,0 +1,120 @@
+use crate::{PlayingMusic, Replay, ReplayPlayer};
+use std::time::Duration;
+
+/// how much the live session is ahead of the ghost. Both are negative when the live session is
+/// late.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct GhostLead {
+    /// the difference of position in the transformed text
+    pub chars: isize,
+    /// the time the ghost will take to reach the live position, or, when the live session is
+    /// late, minus the time since the ghost was at the live position
+    pub seconds: f64,
+}
+
+/// a previous run of a song, played alongside the live session so the player can race against it
+pub struct Ghost {
+    player: ReplayPlayer,
+    music: PlayingMusic,
+    /// the position of the ghost in the transformed text after each keystroke
+    progress: Vec<(Duration, usize)>,
+}
+
+impl Ghost {
+    /// create a ghost of the given replay, for the song of the live PlayingMusic. Return None if
+    /// the replay was recorded on another song.
+    pub fn new(replay: Replay, live: &PlayingMusic) -> Option<Self> {
+        if !replay.is_for(live) {
+            return None;
+        };
+        let mut music = live.clone();
+        let mut player = ReplayPlayer::new(replay);
+        player.prepare(&mut music);
+        let keystroke_times: Vec<Duration> = player
+            .replay()
+            .keystrokes
+            .iter()
+            .map(|keystroke| keystroke.duration())
+            .collect();
+        let mut progress = Vec::new();
+        for time in keystroke_times {
+            player.seek(&mut music, time);
+            progress.push((time, music.position_in_transformed_text()));
+        }
+        player.prepare(&mut music);
+        Some(Self {
+            player,
+            music,
+            progress,
+        })
+    }
+
+    /// move the ghost to the given time of the session
+    pub fn sync(&mut self, session_time: Duration) {
+        if session_time != self.player.clock() {
+            self.player.seek(&mut self.music, session_time);
+        };
+    }
+
+    /// the PlayingMusic of the ghost, to display its position
+    pub fn music(&self) -> &PlayingMusic {
+        &self.music
+    }
+
+    /// the time at which the ghost first reached the given position in the transformed text, or
+    /// the end of the replay if it never reached it
+    pub fn time_to_reach(&self, position: usize) -> Duration {
+        if position == 0 {
+            return Duration::default();
+        };
+        self.progress
+            .iter()
+            .find(|(_, ghost_position)| *ghost_position >= position)
+            .map(|(time, _)| *time)
+            .unwrap_or_else(|| self.player.replay().duration())
+    }
+
+    pub fn lead(&self, live: &PlayingMusic) -> GhostLead {
+        let live_position = live.position_in_transformed_text();
+        let ghost_position = self.music.position_in_transformed_text();
+        let ghost_time = self.time_to_reach(live_position).as_secs_f64();
+        GhostLead {
+            chars: live_position as isize - ghost_position as isize,
+            seconds: ghost_time - live.elapsed_time().as_secs_f64(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Ghost, PlayingMusic, Replay};
+    use std::time::Duration;
+
+    #[test]
+    fn test_ghost_lead() {
+        let song = "hello world, this is a test";
+        let mut best_run = PlayingMusic::new(song.to_string());
+        for (count, chara) in song.chars().enumerate() {
+            best_run.set_elapsed_time(Duration::from_secs(count as u64));
+            best_run.add_typed_char(chara);
+        }
+        let replay = Replay::from_playing_music(&best_run, "test".into());
+        assert!(Ghost::new(replay.clone(), &PlayingMusic::new("other".into())).is_none());
+
+        let mut live = PlayingMusic::new(song.to_string());
+        let mut ghost = Ghost::new(replay, &live).unwrap();
+        live.set_elapsed_time(Duration::from_secs(3));
+        live.add_typed_text("hello w");
+        ghost.sync(live.elapsed_time());
+        assert_eq!(ghost.music().get_typed_text(), "hell");
+        let lead = ghost.lead(&live);
+        assert_eq!(lead.chars, 3);
+        assert!((lead.seconds - 3.0).abs() < 0.0000001);
+
+        live.set_elapsed_time(Duration::from_secs(10));
+        ghost.sync(live.elapsed_time());
+        let lead = ghost.lead(&live);
+        assert_eq!(lead.chars, -4);
+        assert!((lead.seconds + 4.0).abs() < 0.0000001);
+    }
+}
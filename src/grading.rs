@@ -0,0 +1,237 @@
+use crate::{AlignmentStatus, FinishCriteria, Lyrics, PlayingMusic};
+use serde::Serialize;
+use std::fmt::Write;
+
+/// how a word of the source text was typed
+#[derive(Debug, PartialEq, Copy, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WordStatus {
+    Correct,
+    /// some of its characters are substituted, missing or extra
+    Wrong,
+    /// none of its characters was typed
+    Missing,
+}
+
+/// a word of the transformed source text, compared with the typed characters aligned with it
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct WordDiff {
+    /// the source line the word is on
+    pub line: usize,
+    pub expected: String,
+    /// the typed characters aligned with the word, including the extra characters typed in it
+    /// or right after it
+    pub typed: String,
+    pub status: WordStatus,
+}
+
+/// the grade of a typed text, compared with the whole source text as if the song was finished
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct GradeReport {
+    pub correctness: f64,
+    /// the levenshtein distance between the typed text and the source text
+    pub distance: u32,
+    /// the position in the source text the typed text reach, in characters
+    pub position_in_source_text: usize,
+    /// the number of characters of the transformed source text and typed text
+    pub source_char_count: usize,
+    pub typed_char_count: usize,
+    pub words: Vec<WordDiff>,
+}
+
+impl GradeReport {
+    /// type the whole text in a PlayingMusic of the lyrics, then finish it
+    pub fn new(lyrics: Lyrics, typed_text: &str) -> Self {
+        let mut playing_music = PlayingMusic::from_lyrics(lyrics);
+        // the characters typed after the end of the lyrics are mistakes too
+        playing_music.set_finish_criteria(FinishCriteria {
+            end_of_text: false,
+            time_limit: None,
+        });
+        playing_music.add_typed_text(typed_text);
+        playing_music.finish();
+        let result = playing_music
+            .result()
+            .expect("a finished PlayingMusic has a result");
+        Self {
+            correctness: result.correctness,
+            distance: result.distance,
+            position_in_source_text: result.position_in_source_text,
+            source_char_count: result.source_char_count,
+            typed_char_count: result.typed_char_count,
+            words: word_diff(&playing_music),
+        }
+    }
+
+    /// the number of words of each status, in the order correct, wrong and missing
+    pub fn word_counts(&self) -> (usize, usize, usize) {
+        let count = |status| {
+            self.words
+                .iter()
+                .filter(|word| word.status == status)
+                .count()
+        };
+        (
+            count(WordStatus::Correct),
+            count(WordStatus::Wrong),
+            count(WordStatus::Missing),
+        )
+    }
+
+    /// the words of every line, in the format of git diff --word-diff: the wrong words are
+    /// written as [-expected-]{+typed+}, and the missing ones as [-expected-]
+    pub fn word_diff_text(&self) -> String {
+        let mut text = String::new();
+        let mut previous_line = None;
+        for word in &self.words {
+            match previous_line {
+                Some(line) if line == word.line => text.push(' '),
+                Some(_) => text.push('\n'),
+                None => (),
+            };
+            previous_line = Some(word.line);
+            let _ = match word.status {
+                WordStatus::Correct => write!(text, "{}", word.expected),
+                WordStatus::Wrong => write!(text, "[-{}-]{{+{}+}}", word.expected, word.typed),
+                WordStatus::Missing => write!(text, "[-{}-]", word.expected),
+            };
+        }
+        text
+    }
+}
+
+/// a word being built by word_diff
+struct PendingWord {
+    line: usize,
+    expected: String,
+    typed: String,
+    errors: usize,
+}
+
+impl PendingWord {
+    fn into_word_diff(self) -> WordDiff {
+        let status = if self.errors == 0 {
+            WordStatus::Correct
+        } else if self.typed.is_empty() {
+            WordStatus::Missing
+        } else {
+            WordStatus::Wrong
+        };
+        WordDiff {
+            line: self.line,
+            expected: self.expected,
+            typed: self.typed,
+            status,
+        }
+    }
+}
+
+/// compare every word of the transformed source text with the typed characters aligned with it,
+/// the whole text being aligned. The typed characters that are aligned with a space or that are
+/// extra between two words count as a mistake of the previous word, or of the first word if
+/// they are at the beginning. A missing space isn't a mistake of any word.
+fn word_diff(playing_music: &PlayingMusic) -> Vec<WordDiff> {
+    let source: Vec<char> = playing_music.transformed_text().chars().collect();
+    let typed: Vec<char> = playing_music.get_typed_text().chars().collect();
+    let mut words: Vec<PendingWord> = Vec::new();
+    // true while the characters are added to the last word
+    let mut in_word = false;
+    // the typed characters before the first word
+    let mut leading_typed = String::new();
+    let mut leading_errors = 0;
+    for aligned in playing_music.alignment(true) {
+        let typed_char = aligned.typed_index.map(|index| typed[index]);
+        let source_char = aligned.source_index.map(|index| source[index]);
+        let error = aligned.status != AlignmentStatus::Correct;
+        match source_char {
+            Some(chara) if chara != ' ' => {
+                if !in_word {
+                    let line = aligned
+                        .source_index
+                        .and_then(|index| playing_music.transformed_line(index))
+                        .unwrap_or(0);
+                    words.push(PendingWord {
+                        line,
+                        expected: String::new(),
+                        typed: std::mem::take(&mut leading_typed),
+                        errors: std::mem::take(&mut leading_errors),
+                    });
+                    in_word = true;
+                };
+                // a word was pushed just above
+                let word = words.last_mut().unwrap();
+                word.expected.push(chara);
+                if let Some(typed_char) = typed_char {
+                    word.typed.push(typed_char);
+                };
+                if error {
+                    word.errors += 1;
+                };
+            }
+            _ => {
+                if source_char.is_some() {
+                    in_word = false;
+                };
+                match words.last_mut() {
+                    Some(word) => {
+                        if let Some(typed_char) = typed_char.filter(|chara| *chara != ' ') {
+                            word.typed.push(typed_char);
+                        };
+                        if error && aligned.status != AlignmentStatus::Missing {
+                            word.errors += 1;
+                        };
+                    }
+                    None => {
+                        if let Some(typed_char) = typed_char.filter(|chara| *chara != ' ') {
+                            leading_typed.push(typed_char);
+                        };
+                        if error && aligned.status != AlignmentStatus::Missing {
+                            leading_errors += 1;
+                        };
+                    }
+                };
+            }
+        };
+    }
+    words.into_iter().map(PendingWord::into_word_diff).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{GradeReport, Lyrics, WordStatus};
+
+    #[test]
+    fn test_grade_report() {
+        let lyrics = Lyrics::plain("Hello world\nthe second line");
+        let report = GradeReport::new(lyrics.clone(), "hello wrld the second line");
+        assert_eq!(report.distance, 1);
+        assert!(report.correctness < 1.0 && report.correctness > 0.9);
+        assert_eq!(report.word_counts(), (4, 1, 0));
+        assert_eq!(report.words[1].expected, "world");
+        assert_eq!(report.words[1].typed, "wrld");
+        assert_eq!(report.words[1].status, WordStatus::Wrong);
+        assert_eq!(report.words[2].line, 1);
+        assert_eq!(
+            report.word_diff_text(),
+            "hello [-world-]{+wrld+}\nthe second line"
+        );
+
+        let report = GradeReport::new(lyrics.clone(), "hello world");
+        assert_eq!(report.word_counts(), (2, 0, 3));
+        assert_eq!(report.words[4].status, WordStatus::Missing);
+
+        let report = GradeReport::new(lyrics, "hello world the second line");
+        assert_eq!(report.correctness, 1.0);
+        assert_eq!(report.word_counts(), (5, 0, 0));
+    }
+
+    #[test]
+    fn test_grade_report_extra_characters() {
+        let lyrics = Lyrics::plain("one two");
+        let report = GradeReport::new(lyrics, "xone twoo");
+        assert_eq!(report.words[0].typed, "xone");
+        assert_eq!(report.words[0].status, WordStatus::Wrong);
+        assert_eq!(report.words[1].typed, "twoo");
+        assert_eq!(report.words[1].status, WordStatus::Wrong);
+    }
+}
@@ -1,4 +1,5 @@
 /// a structure to do incremental levenshtein operation that does support poping a character.
+#[derive(Clone)]
 struct IncrementalLevenshtein {
     source: Vec<char>,
     other: Vec<char>,
@@ -147,6 +148,7 @@ impl IncrementalLevenshtein {
     }
 }
 
+#[derive(Clone)]
 pub struct DistanceIncremental {
     levenshtein: IncrementalLevenshtein,
 }
@@ -175,6 +177,7 @@ impl DistanceIncremental {
     }
 }
 
+#[derive(Clone)]
 pub struct PositionIncremental {
     levenshtein: IncrementalLevenshtein,
 }
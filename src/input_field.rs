@@ -0,0 +1,127 @@
+use crate::PlayingMusic;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// a grapheme of the typed text, as displayed in an input field
+#[derive(Debug, PartialEq, Clone)]
+pub struct InputGrapheme {
+    pub text: String,
+    /// true if one of its characters is considered wrong by the aligner
+    pub mistake: bool,
+}
+
+/// what an input field display of the typed text: its end, that fit in the field, cut between
+/// graphemes
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct InputFieldView {
+    pub graphemes: Vec<InputGrapheme>,
+    /// true if the beginning of the typed text doesn't fit in the field
+    pub truncated: bool,
+    /// true if a space was typed but is not in the typed text yet (see
+    /// PlayingMusic::has_pending_space)
+    pub pending_space: bool,
+}
+
+impl InputFieldView {
+    /// the end of the typed text of the PlayingMusic that fit in max_width columns (as given by
+    /// unicode-width). One column is kept for the pending space.
+    pub fn new(playing_music: &PlayingMusic, max_width: usize) -> Self {
+        let typed_text = playing_music.get_typed_text();
+        let pending_space = playing_music.has_pending_space();
+        let max_width = max_width.saturating_sub(1);
+        let mut width = 0;
+        let mut visible = Vec::new();
+        for grapheme in typed_text.graphemes(true).rev() {
+            let grapheme_width = UnicodeWidthStr::width(grapheme);
+            if width + grapheme_width > max_width {
+                break;
+            };
+            width += grapheme_width;
+            visible.push(grapheme);
+        }
+        visible.reverse();
+        let visible_len: usize = visible
+            .iter()
+            .map(|grapheme| grapheme.chars().count())
+            .sum();
+        let typed_len = typed_text.chars().count();
+        let mistakes = playing_music.typed_mistakes(typed_len - visible_len);
+        let mut char_index = 0;
+        let graphemes = visible
+            .into_iter()
+            .map(|grapheme| {
+                let char_count = grapheme.chars().count();
+                let mistake = mistakes[char_index..char_index + char_count]
+                    .iter()
+                    .any(|mistake| *mistake);
+                char_index += char_count;
+                InputGrapheme {
+                    text: grapheme.to_string(),
+                    mistake,
+                }
+            })
+            .collect();
+        Self {
+            graphemes,
+            truncated: visible_len < typed_len,
+            pending_space,
+        }
+    }
+
+    /// the width of the displayed text, in columns, including the pending space
+    pub fn width(&self) -> usize {
+        self.graphemes
+            .iter()
+            .map(|grapheme| UnicodeWidthStr::width(grapheme.text.as_str()))
+            .sum::<usize>()
+            + if self.pending_space { 1 } else { 0 }
+    }
+
+    /// the displayed graphemes, merged in runs of the same mistake status, as (text, mistake)
+    pub fn runs(&self) -> Vec<(String, bool)> {
+        let mut runs: Vec<(String, bool)> = Vec::new();
+        for grapheme in &self.graphemes {
+            match runs.last_mut() {
+                Some((text, mistake)) if *mistake == grapheme.mistake => {
+                    text.push_str(&grapheme.text)
+                }
+                _ => runs.push((grapheme.text.clone(), grapheme.mistake)),
+            };
+        }
+        runs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{InputFieldView, PlayingMusic};
+
+    #[test]
+    fn test_input_field_view() {
+        let mut playing_music = PlayingMusic::new("hello 世界 cafe\u{301} ok".into());
+        playing_music.add_typed_text("hellp 世界 cafe\u{301}, ");
+        let view = InputFieldView::new(&playing_music, 12);
+        assert!(view.truncated);
+        assert!(view.pending_space);
+        // " 世界 café" is 10 columns, the pending space being the last column
+        assert_eq!(
+            view.graphemes
+                .iter()
+                .map(|grapheme| grapheme.text.as_str())
+                .collect::<String>(),
+            "p 世界 cafe\u{301}"
+        );
+        assert_eq!(view.width(), 12);
+        assert_eq!(
+            view.runs(),
+            vec![
+                ("p".to_string(), true),
+                (" 世界 cafe\u{301}".to_string(), false)
+            ]
+        );
+
+        let view = InputFieldView::new(&playing_music, 100);
+        assert!(!view.truncated);
+        assert_eq!(view.graphemes.len(), 13);
+    }
+}
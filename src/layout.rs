@@ -0,0 +1,154 @@
+use crate::Settings;
+
+/// the height of the window the reference font size is made for, in logical pixels
+const REFERENCE_HEIGHT: f32 = 1080.0;
+/// the font size of the lyrics in a window of the reference height
+const REFERENCE_FONT_SIZE: f32 = 64.0;
+/// the number of columns of the lyrics that must fit in the width of the window
+const MINIMUM_COLUMNS: f32 = 60.0;
+const MINIMUM_FONT_SIZE: f32 = 16.0;
+/// the distance between two lines, relative to the font size
+const LINE_SPACING: f32 = 1.5625;
+/// the width of a column of text, relative to the font size
+pub const COLUMN_WIDTH_RATIO: f32 = 0.5;
+
+/// the size and position of the lyrics, computed from the size of the window so that the game
+/// look the same whatever the resolution. Every length is in logical pixels.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ScreenLayout {
+    pub font_size: f32,
+    pub distance_between_line: f32,
+    /// the y coordinate between the typed lines and the lines to type, where the input field is
+    pub current_y: f32,
+    /// the number of typed lines displayed above current_y
+    pub top_displayed_line: usize,
+    /// the number of lines to type displayed below the current line
+    pub bottom_displayed_line: usize,
+    /// the width of the input field, in columns
+    pub input_field_columns: usize,
+    /// the width longer lines are wrapped at
+    pub max_line_width: f32,
+}
+
+impl ScreenLayout {
+    /// compute the layout of a window of the given logical size. The font size is rounded so
+    /// that it is a whole number of physical pixels.
+    pub fn new(width: f32, height: f32, scale_factor: f64) -> Self {
+        Self::with_sizes(
+            width,
+            height,
+            scale_factor,
+            REFERENCE_FONT_SIZE,
+            REFERENCE_FONT_SIZE * LINE_SPACING,
+        )
+    }
+
+    /// compute the layout with the font size and the distance between lines of the settings as
+    /// the sizes of a window of the reference height, and with at most the numbers of lines of
+    /// the settings
+    pub fn from_settings(width: f32, height: f32, scale_factor: f64, settings: &Settings) -> Self {
+        let display = &settings.display;
+        let mut layout = Self::with_sizes(
+            width,
+            height,
+            scale_factor,
+            settings.font_size,
+            display.distance_between_line,
+        );
+        layout.top_displayed_line = layout.top_displayed_line.min(display.top_displayed_line);
+        layout.bottom_displayed_line = layout
+            .bottom_displayed_line
+            .min(display.bottom_displayed_line);
+        layout
+    }
+
+    fn with_sizes(
+        width: f32,
+        height: f32,
+        scale_factor: f64,
+        reference_font_size: f32,
+        reference_distance_between_line: f32,
+    ) -> Self {
+        let scale_factor = scale_factor.max(0.1) as f32;
+        let font_size = (height * reference_font_size / REFERENCE_HEIGHT)
+            .min(width / (MINIMUM_COLUMNS * COLUMN_WIDTH_RATIO))
+            .max(MINIMUM_FONT_SIZE);
+        let font_size = (font_size * scale_factor).round() / scale_factor;
+        let distance_between_line =
+            font_size * reference_distance_between_line / reference_font_size;
+        let current_y = height / 2.0;
+        // a line is kept above and below the displayed lines, for the lines that fade in or out
+        // and for the header
+        let top_displayed_line =
+            ((current_y - font_size) / distance_between_line).floor() as isize - 1;
+        let bottom_displayed_line =
+            ((height - current_y - font_size) / distance_between_line).floor() as isize - 1;
+        let input_field_columns =
+            (width / 2.0 / (font_size * COLUMN_WIDTH_RATIO)).floor() as isize - 2;
+        Self {
+            font_size,
+            distance_between_line,
+            current_y,
+            top_displayed_line: top_displayed_line.max(0) as usize,
+            bottom_displayed_line: bottom_displayed_line.max(0) as usize,
+            input_field_columns: input_field_columns.max(1) as usize,
+            max_line_width: width - font_size,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ScreenLayout, Settings};
+
+    #[test]
+    fn test_screen_layout() {
+        let full_hd = ScreenLayout::new(1920.0, 1080.0, 1.0);
+        assert_eq!(full_hd.font_size, 64.0);
+        assert_eq!(full_hd.distance_between_line, 100.0);
+        assert_eq!(full_hd.current_y, 540.0);
+        assert_eq!(full_hd.top_displayed_line, 3);
+        assert_eq!(full_hd.bottom_displayed_line, 3);
+        assert_eq!(full_hd.input_field_columns, 28);
+        assert_eq!(full_hd.max_line_width, 1856.0);
+
+        // a 4K screen with a scale factor of 2 has the same logical size
+        assert_eq!(ScreenLayout::new(1920.0, 1080.0, 2.0), full_hd);
+
+        let hd = ScreenLayout::new(1280.0, 720.0, 1.0);
+        assert!((hd.font_size - 43.0).abs() < 0.0001);
+        assert_eq!(hd.top_displayed_line, 3);
+        assert_eq!(hd.bottom_displayed_line, 3);
+
+        // an ultrawide window doesn't make the text bigger, but the input field wider
+        let ultrawide = ScreenLayout::new(3440.0, 1440.0, 1.0);
+        assert!(ultrawide.font_size > full_hd.font_size);
+        assert!(ultrawide.input_field_columns > full_hd.input_field_columns);
+        let ultrawide_low = ScreenLayout::new(2560.0, 1080.0, 1.0);
+        assert_eq!(ultrawide_low.font_size, full_hd.font_size);
+        assert!(ultrawide_low.input_field_columns > full_hd.input_field_columns);
+
+        // a narrow window make the text smaller so that the lines fit
+        let narrow = ScreenLayout::new(600.0, 1080.0, 1.0);
+        assert_eq!(narrow.font_size, 20.0);
+        assert!(narrow.bottom_displayed_line > full_hd.bottom_displayed_line);
+    }
+
+    #[test]
+    fn test_screen_layout_from_settings() {
+        let mut settings = Settings::default();
+        assert_eq!(
+            ScreenLayout::from_settings(1920.0, 1080.0, 1.0, &settings),
+            ScreenLayout::new(1920.0, 1080.0, 1.0)
+        );
+        settings.font_size = 32.0;
+        settings.display.distance_between_line = 64.0;
+        settings.display.top_displayed_line = 5;
+        settings.display.bottom_displayed_line = 2;
+        let small = ScreenLayout::from_settings(1920.0, 1080.0, 1.0, &settings);
+        assert_eq!(small.font_size, 32.0);
+        assert_eq!(small.distance_between_line, 64.0);
+        assert_eq!(small.top_displayed_line, 5);
+        assert_eq!(small.bottom_displayed_line, 2);
+    }
+}
@@ -1,5 +1,66 @@
 mod playing_music;
-pub use playing_music::PlayingMusic;
+pub use playing_music::{song_hash, PlayingMusic};
 
 mod incremental_levenshtein;
 pub use incremental_levenshtein::{DistanceIncremental, PositionIncremental};
+
+mod chordpro;
+pub use chordpro::{ChordAnnotation, ChordProLine, ChordProSection, ChordProSong, SongMetadata};
+
+mod lyrics;
+pub use lyrics::{LyricLine, Lyrics, Section};
+
+mod alignment;
+pub use alignment::{align, align_window, AlignedChar, AlignmentStatus, DiffRun};
+
+mod session;
+pub use session::{FinishCriteria, FinishReason, SessionResult};
+
+mod stats;
+pub use stats::{rolling_speed, Keystroke, KeystrokeKind, TypingStats};
+
+mod replay;
+pub use replay::{Replay, ReplayKeystroke, ReplayPlayer, ReplaySettings};
+
+mod ghost;
+pub use ghost::{Ghost, GhostLead};
+
+pub mod paths;
+
+mod score_database;
+pub use score_database::{ScoreDatabase, ScoreRecord};
+
+mod library;
+pub use library::{LibraryEntry, LibrarySearch, SongLibrary, SongSort};
+
+mod input_field;
+pub use input_field::{InputFieldView, InputGrapheme};
+
+mod scroll;
+pub use scroll::{ScrollAnimation, ScrollEasing};
+
+mod layout;
+pub use layout::{ScreenLayout, COLUMN_WIDTH_RATIO};
+
+mod wrap;
+pub use wrap::{wrap_line, VisualRow, WrappedLines};
+
+mod progress;
+pub use progress::{ProgressEvent, ProgressTracker};
+
+mod grading;
+pub use grading::{GradeReport, WordDiff, WordStatus};
+
+mod settings;
+pub use settings::{DisplaySettings, GameMode, Settings, SettingsItem, SettingsOverrides};
+
+mod theme;
+pub use theme::{EmphasisStyle, RgbaColor, Theme, TypingColors};
+
+mod font_stack;
+pub use font_stack::FontStack;
+
+#[cfg(feature = "gui")]
+pub mod game;
+#[cfg(feature = "gui")]
+pub use game::{InputSource, MusicTyperCommand, MusicTyperPlugin, MusicTyperSystems};
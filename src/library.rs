@@ -0,0 +1,456 @@
+use crate::{paths, song_hash, ChordProLine, ChordProSection, ChordProSong, DistanceIncremental};
+use crate::{Lyrics, PlayingMusic, SongMetadata};
+use log::warn;
+use std::cmp::Ordering;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// the extensions of the files read as ChordPro songs. Files ending with .txt are read as plain
+/// lyrics.
+static CHORDPRO_EXTENSIONS: [&str; 3] = ["cho", "chopro", "chordpro"];
+
+/// a song of a SongLibrary
+#[derive(Debug, PartialEq, Clone)]
+pub struct LibraryEntry {
+    /// the file the song was read from, if any
+    pub path: Option<PathBuf>,
+    /// the metadata and chords of the song. Songs read from plain lyrics have no chords, and
+    /// their title is the name of their file.
+    pub song: ChordProSong,
+    /// the lyrics that are typed
+    pub lyrics: Lyrics,
+    /// the hash of the source text (see PlayingMusic::song_hash)
+    pub song_hash: u64,
+}
+
+impl LibraryEntry {
+    pub fn from_chordpro(song: ChordProSong, path: Option<PathBuf>) -> Self {
+        let lyrics = song.to_lyrics();
+        Self {
+            path,
+            song_hash: song_hash(&lyrics.text()),
+            song,
+            lyrics,
+        }
+    }
+
+    pub fn from_lyrics(lyrics: Lyrics, title: Option<String>, path: Option<PathBuf>) -> Self {
+        let song = ChordProSong {
+            metadata: SongMetadata {
+                title,
+                ..Default::default()
+            },
+            lines: lyrics
+                .lines
+                .iter()
+                .map(|line| ChordProLine {
+                    lyrics: line.text.clone(),
+                    chords: Vec::new(),
+                })
+                .collect(),
+            sections: Vec::new(),
+        };
+        Self {
+            path,
+            song_hash: song_hash(&lyrics.text()),
+            song,
+            lyrics,
+        }
+    }
+
+    /// read a song from a file, in the ChordPro format or as plain lyrics depending on its
+    /// extension
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let source = fs::read_to_string(path)?;
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+        if CHORDPRO_EXTENSIONS.contains(&extension.as_str()) {
+            Ok(Self::from_chordpro(
+                ChordProSong::parse(&source),
+                Some(path.to_path_buf()),
+            ))
+        } else {
+            let title = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned());
+            Ok(Self::from_lyrics(
+                Lyrics::parse(&source),
+                title,
+                Some(path.to_path_buf()),
+            ))
+        }
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.song.metadata.title.as_deref()
+    }
+
+    pub fn artist(&self) -> Option<&str> {
+        self.song.metadata.artist.as_deref()
+    }
+
+    /// an estimation of how hard the song is to type: the number of characters to type, upper
+    /// case letters, digits and punctuation counting double. It can be overridden with a
+    /// {difficulty: <number>} directive.
+    pub fn difficulty(&self) -> f64 {
+        let overridden = self
+            .song
+            .metadata
+            .other
+            .iter()
+            .find(|(name, _)| name == "difficulty")
+            .and_then(|(_, value)| value.trim().parse().ok());
+        if let Some(difficulty) = overridden {
+            return difficulty;
+        };
+        self.lyrics
+            .lines
+            .iter()
+            .filter(|line| line.typed)
+            .flat_map(|line| line.text.chars())
+            .filter(|chara| !chara.is_whitespace())
+            .map(|chara| if chara.is_lowercase() { 1.0 } else { 2.0 })
+            .sum()
+    }
+
+    pub fn to_playing_music(&self) -> PlayingMusic {
+        PlayingMusic::from_lyrics(self.lyrics.clone())
+    }
+
+    /// the song from the given line to the end. Its hash is the one of the remaining lyrics, so
+    /// its scores are not mixed with the ones of the whole song.
+    pub fn starting_at_line(&self, first_line: usize) -> Self {
+        let lyrics = self.lyrics.starting_at_line(first_line);
+        // the lines of the song are the lines of the lyrics
+        let first_line = first_line.min(self.song.lines.len());
+        let song = ChordProSong {
+            metadata: self.song.metadata.clone(),
+            lines: self.song.lines[first_line..].to_vec(),
+            sections: self
+                .song
+                .sections
+                .iter()
+                .filter(|section| section.end_line > first_line)
+                .map(|section| ChordProSection {
+                    first_line: section.first_line.max(first_line) - first_line,
+                    end_line: section.end_line - first_line,
+                    ..section.clone()
+                })
+                .collect(),
+        };
+        Self {
+            path: self.path.clone(),
+            song_hash: song_hash(&lyrics.text()),
+            song,
+            lyrics,
+        }
+    }
+
+    /// the text searched by a LibrarySearch
+    fn search_text(&self) -> String {
+        let mut text = self.title().unwrap_or_default().to_string();
+        if let Some(artist) = self.artist() {
+            text.push(' ');
+            text.push_str(artist);
+        };
+        text.to_lowercase()
+    }
+}
+
+/// how the songs of a SongLibrary are sorted
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum SongSort {
+    Title,
+    /// by artist, then by title
+    Artist,
+    /// easiest first
+    Difficulty,
+    /// best correctness first, the songs never played being last
+    PersonalBest,
+}
+
+impl SongSort {
+    /// the next way to sort, to cycle through them
+    pub fn next(self) -> Self {
+        match self {
+            SongSort::Title => SongSort::Artist,
+            SongSort::Artist => SongSort::Difficulty,
+            SongSort::Difficulty => SongSort::PersonalBest,
+            SongSort::PersonalBest => SongSort::Title,
+        }
+    }
+}
+
+/// compare two optional texts, ignoring case, None being last
+fn compare_text(first: Option<&str>, second: Option<&str>) -> Ordering {
+    match (first, second) {
+        (Some(first), Some(second)) => first.to_lowercase().cmp(&second.to_lowercase()),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// the songs that can be played
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct SongLibrary {
+    pub entries: Vec<LibraryEntry>,
+}
+
+impl SongLibrary {
+    pub fn new(entries: Vec<LibraryEntry>) -> Self {
+        Self { entries }
+    }
+
+    /// the directory the songs are read from by default
+    pub fn default_directory() -> Option<PathBuf> {
+        paths::data_directory().map(|directory| directory.join("songs"))
+    }
+
+    /// read every song of the directory. The files that can't be read are skipped, and a
+    /// directory that doesn't exist is an empty library.
+    pub fn load(directory: &Path) -> io::Result<Self> {
+        let read_dir = match fs::read_dir(directory) {
+            Ok(read_dir) => read_dir,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err),
+        };
+        let mut paths = Vec::new();
+        for dir_entry in read_dir {
+            let path = dir_entry?.path();
+            let is_song = path
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .map(|extension| {
+                    let extension = extension.to_lowercase();
+                    extension == "txt" || CHORDPRO_EXTENSIONS.contains(&extension.as_str())
+                })
+                .unwrap_or(false);
+            if is_song && path.is_file() {
+                paths.push(path);
+            };
+        }
+        paths.sort();
+        let mut entries = Vec::new();
+        for path in paths {
+            match LibraryEntry::load(&path) {
+                Ok(entry) => entries.push(entry),
+                Err(err) => warn!("can't read the song {:?}: {}", path, err),
+            };
+        }
+        Ok(Self::new(entries))
+    }
+
+    /// the index of the entries, sorted. personal_best return the best correctness of a song
+    /// hash, if it was played.
+    pub fn sorted(&self, sort: SongSort, personal_best: impl Fn(u64) -> Option<f64>) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.entries.len()).collect();
+        let by_title = |first: &LibraryEntry, second: &LibraryEntry| {
+            compare_text(first.title(), second.title())
+        };
+        match sort {
+            SongSort::Title => order
+                .sort_by(|first, second| by_title(&self.entries[*first], &self.entries[*second])),
+            SongSort::Artist => order.sort_by(|first, second| {
+                let (first, second) = (&self.entries[*first], &self.entries[*second]);
+                compare_text(first.artist(), second.artist()).then_with(|| by_title(first, second))
+            }),
+            SongSort::Difficulty => {
+                let difficulties: Vec<f64> = self
+                    .entries
+                    .iter()
+                    .map(|entry| entry.difficulty())
+                    .collect();
+                order.sort_by(|first, second| {
+                    difficulties[*first]
+                        .partial_cmp(&difficulties[*second])
+                        .unwrap_or(Ordering::Equal)
+                })
+            }
+            SongSort::PersonalBest => {
+                let bests: Vec<Option<f64>> = self
+                    .entries
+                    .iter()
+                    .map(|entry| personal_best(entry.song_hash))
+                    .collect();
+                order.sort_by(|first, second| match (bests[*first], bests[*second]) {
+                    (Some(first), Some(second)) => {
+                        second.partial_cmp(&first).unwrap_or(Ordering::Equal)
+                    }
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                })
+            }
+        };
+        order
+    }
+}
+
+/// An incremental, typo tolerant search of the songs of a library by title and artist. The
+/// query is matched against the whole searched text, so the characters of the searched text that
+/// are not in the query don't count as errors, as long as the query is in the same order.
+pub struct LibrarySearch {
+    query: String,
+    /// the distance between the query and the searched text of every entry, with the length of
+    /// this text
+    distances: Vec<(DistanceIncremental, usize)>,
+}
+
+impl LibrarySearch {
+    pub fn new(library: &SongLibrary) -> Self {
+        Self {
+            query: String::new(),
+            distances: library
+                .entries
+                .iter()
+                .map(|entry| {
+                    let search_text = entry.search_text();
+                    let search_text_len = search_text.chars().count();
+                    (DistanceIncremental::new(&search_text), search_text_len)
+                })
+                .collect(),
+        }
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn push(&mut self, chara: char) {
+        for lower_chara in chara.to_lowercase() {
+            self.query.push(lower_chara);
+            for (distance, _) in self.distances.iter_mut() {
+                distance.add_other_char(lower_chara);
+            }
+        }
+    }
+
+    pub fn pop(&mut self) {
+        if self.query.pop().is_some() {
+            for (distance, _) in self.distances.iter_mut() {
+                distance.pop_other_char();
+            }
+        };
+    }
+
+    pub fn clear(&mut self) {
+        while !self.query.is_empty() {
+            self.pop();
+        }
+    }
+
+    /// the number of typo in the query for the given entry, or None if it doesn't match
+    pub fn errors(&self, entry: usize) -> Option<u32> {
+        let (distance, search_text_len) = self.distances.get(entry)?;
+        let query_len = self.query.chars().count();
+        // the characters of the searched text that are not in the query are free
+        let errors = distance.distance() - search_text_len.saturating_sub(query_len) as u32;
+        // one typo allowed every four characters
+        if errors as usize <= query_len / 4 {
+            Some(errors)
+        } else {
+            None
+        }
+    }
+
+    /// keep the entries of order that match the query, the ones with the fewest typo first
+    pub fn filter(&self, order: &[usize]) -> Vec<usize> {
+        let mut matching: Vec<(usize, u32)> = order
+            .iter()
+            .filter_map(|entry| self.errors(*entry).map(|errors| (*entry, errors)))
+            .collect();
+        matching.sort_by_key(|(_, errors)| *errors);
+        matching.into_iter().map(|(entry, _)| entry).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ChordProSong, LibraryEntry, LibrarySearch, Lyrics, SongLibrary, SongSort};
+    use std::{env, fs, process};
+
+    fn library() -> SongLibrary {
+        SongLibrary::new(vec![
+            LibraryEntry::from_chordpro(
+                ChordProSong::parse("{title: Light of Your Cutie Mark}\n{artist: My Little Pony}\nSee the light"),
+                None,
+            ),
+            LibraryEntry::from_chordpro(
+                ChordProSong::parse("{title: Bohemian Rhapsody}\n{artist: Queen}\nIs this the real life?\nIs this just fantasy?"),
+                None,
+            ),
+            LibraryEntry::from_lyrics(Lyrics::parse("la la la"), Some("aaa".into()), None),
+        ])
+    }
+
+    #[test]
+    fn test_library_sort() {
+        let library = library();
+        assert_eq!(library.sorted(SongSort::Title, |_| None), vec![2, 1, 0]);
+        assert_eq!(library.sorted(SongSort::Artist, |_| None), vec![0, 1, 2]);
+        assert_eq!(
+            library.sorted(SongSort::Difficulty, |_| None),
+            vec![2, 0, 1]
+        );
+        let bohemian_hash = library.entries[1].song_hash;
+        let personal_best = |hash| {
+            if hash == bohemian_hash {
+                Some(0.5)
+            } else {
+                None
+            }
+        };
+        assert_eq!(library.sorted(SongSort::PersonalBest, personal_best)[0], 1);
+    }
+
+    #[test]
+    fn test_library_search() {
+        let library = library();
+        let mut search = LibrarySearch::new(&library);
+        assert_eq!(search.filter(&[0, 1, 2]), vec![0, 1, 2]);
+        for chara in "Ligt cutie".chars() {
+            search.push(chara);
+        }
+        assert_eq!(search.query(), "ligt cutie");
+        assert_eq!(search.filter(&[0, 1, 2]), vec![0]);
+        search.clear();
+        // a typo in "queen"
+        for chara in "bohemian qeuen".chars() {
+            search.push(chara);
+        }
+        assert_eq!(search.errors(1), Some(2));
+        assert_eq!(search.filter(&[0, 1, 2]), vec![1]);
+        for _ in 0.."qeuen".len() {
+            search.pop();
+        }
+        assert_eq!(search.errors(1), Some(0));
+    }
+
+    #[test]
+    fn test_library_load() {
+        let directory = env::temp_dir().join(format!("music_typer_library_test_{}", process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("b.cho"), "{title: B}\n[C]hello [G]world").unwrap();
+        fs::write(directory.join("a song.txt"), "[Verse]\nhello world").unwrap();
+        fs::write(directory.join("ignored.json"), "{}").unwrap();
+        let library = SongLibrary::load(&directory).unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(library.entries.len(), 2);
+        assert_eq!(library.entries[0].title(), Some("a song"));
+        assert_eq!(library.entries[0].lyrics.lines.len(), 2);
+        assert!(!library.entries[0].lyrics.lines[0].typed);
+        assert_eq!(library.entries[1].title(), Some("B"));
+        assert_eq!(library.entries[1].song.lines[0].chords.len(), 2);
+        assert_eq!(
+            library.entries[1].to_playing_music().source_text(),
+            "hello world"
+        );
+        assert!(SongLibrary::load(&directory).unwrap().entries.is_empty());
+    }
+}
@@ -0,0 +1,374 @@
+/// a line of lyrics. Lines that are not typed (like section headings) are only displayed.
+#[derive(Debug, PartialEq, Clone)]
+pub struct LyricLine {
+    pub text: String,
+    pub typed: bool,
+}
+
+/// a section of a song, like a verse or a chorus. first_line and end_line are index of lines
+/// (end_line being excluded), and doesn't include the heading line.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Section {
+    pub name: String,
+    pub heading_line: Option<usize>,
+    pub first_line: usize,
+    pub end_line: usize,
+}
+
+/// lyrics, splitted into lines and sections, with repeat references expanded
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Lyrics {
+    pub lines: Vec<LyricLine>,
+    pub sections: Vec<Section>,
+}
+
+/// return the content of a line like "[Chorus]"
+fn parse_heading(line: &str) -> Option<&str> {
+    let trimmed = line.trim();
+    if trimmed.len() > 2 && trimmed.starts_with('[') && trimmed.ends_with(']') {
+        let inner = trimmed[1..trimmed.len() - 1].trim();
+        if !inner.is_empty() && !inner.contains(['[', ']']) {
+            return Some(inner);
+        };
+    };
+    None
+}
+
+/// return true if the reference name refer to the given section name. The name is case
+/// insensitive, and "chorus" also match a section called "Chorus 2".
+fn section_name_match(section_name: &str, reference: &str) -> bool {
+    let section_name = section_name.to_lowercase();
+    let reference = reference.to_lowercase();
+    section_name == reference || section_name.starts_with(&format!("{} ", reference))
+}
+
+/// split something like "chorus x2" into ("chorus", 2)
+fn parse_repeat_count(text: &str) -> (String, usize) {
+    let mut words: Vec<&str> = text.split_whitespace().collect();
+    let mut count = 1;
+    if let Some(last) = words.last() {
+        let last = last.to_lowercase();
+        let number = last
+            .strip_prefix('x')
+            .or_else(|| last.strip_prefix('×'))
+            .or_else(|| last.strip_suffix('x'))
+            .and_then(|number| number.parse::<usize>().ok());
+        if let Some(number) = number {
+            count = number;
+            words.pop();
+        } else if let Ok(number) = last.parse::<usize>() {
+            if words.len() >= 2 && ["x", "×"].contains(&words[words.len() - 2]) {
+                count = number;
+                words.pop();
+                words.pop();
+            };
+        };
+    };
+    (words.join(" "), count)
+}
+
+/// return the referenced section name and the number of repetition of a line like
+/// "(Chorus x2)" or "Repeat chorus"
+fn parse_repeat(line: &str) -> Option<(String, usize)> {
+    let trimmed = line.trim();
+    let inner = if trimmed.starts_with('(') && trimmed.ends_with(')') && trimmed.len() > 2 {
+        trimmed[1..trimmed.len() - 1].trim()
+    } else if trimmed.to_lowercase().starts_with("repeat ") {
+        trimmed
+    } else {
+        return None;
+    };
+    let inner = if inner.to_lowercase().starts_with("repeat ") {
+        inner["repeat ".len()..].trim()
+    } else {
+        inner
+    };
+    let (name, count) = parse_repeat_count(inner);
+    if name.is_empty() || count == 0 {
+        None
+    } else {
+        Some((name, count))
+    }
+}
+
+impl Lyrics {
+    /// create lyrics where every line is typed, and without any section
+    pub fn plain(text: &str) -> Self {
+        Self {
+            lines: split_lines(text)
+                .map(|line| LyricLine {
+                    text: line.to_string(),
+                    typed: true,
+                })
+                .collect(),
+            sections: Vec::new(),
+        }
+    }
+
+    /// parse the section headings (like "[Verse 2]"), and expand the repeat references (like
+    /// "(Chorus x2)", "Repeat chorus" or a heading of an already existing section without content)
+    pub fn parse(text: &str) -> Self {
+        let raw_lines: Vec<&str> = split_lines(text).collect();
+        let mut lyrics = Self::default();
+        let mut open_section: Option<(String, usize, usize)> = None;
+        for (line_count, line) in raw_lines.iter().enumerate() {
+            let reference = match parse_heading(line) {
+                Some(heading) => Some(parse_repeat_count(heading)),
+                None => parse_repeat(line),
+            };
+            if let (Some((name, _)), Some((open_name, _, _))) = (&reference, &open_section) {
+                if section_name_match(open_name, name) {
+                    lyrics.close_section(open_section.take());
+                };
+            };
+            let body_is_empty = || {
+                raw_lines[line_count + 1..]
+                    .iter()
+                    .find(|line| !line.trim().is_empty())
+                    .map(|line| parse_heading(line).is_some() || parse_repeat(line).is_some())
+                    .unwrap_or(true)
+            };
+            let repeat = if let Some(heading) = parse_heading(line) {
+                let (name, count) = reference.unwrap_or_default();
+                match lyrics.find_section(&name) {
+                    Some(section) if body_is_empty() => Some((section, count)),
+                    _ => {
+                        lyrics.close_section(open_section.take());
+                        lyrics.lines.push(LyricLine {
+                            text: line.to_string(),
+                            typed: false,
+                        });
+                        open_section = Some((
+                            heading.to_string(),
+                            lyrics.lines.len() - 1,
+                            lyrics.lines.len(),
+                        ));
+                        continue;
+                    }
+                }
+            } else if let Some((name, count)) = reference {
+                lyrics.find_section(&name).map(|section| (section, count))
+            } else {
+                None
+            };
+            match repeat {
+                Some((section_id, count)) => {
+                    lyrics.close_section(open_section.take());
+                    lyrics.repeat_section(section_id, count);
+                }
+                None => lyrics.lines.push(LyricLine {
+                    text: line.to_string(),
+                    typed: true,
+                }),
+            }
+        }
+        lyrics.close_section(open_section.take());
+        lyrics
+    }
+
+    fn close_section(&mut self, section: Option<(String, usize, usize)>) {
+        if let Some((name, heading_line, first_line)) = section {
+            let mut end_line = self.lines.len();
+            while end_line > first_line && self.lines[end_line - 1].text.trim().is_empty() {
+                end_line -= 1;
+            }
+            self.sections.push(Section {
+                name,
+                heading_line: Some(heading_line),
+                first_line,
+                end_line,
+            });
+        };
+    }
+
+    /// find the last section that match the given name
+    fn find_section(&self, name: &str) -> Option<usize> {
+        self.sections
+            .iter()
+            .rposition(|section| section_name_match(&section.name, name))
+    }
+
+    fn repeat_section(&mut self, section_id: usize, count: usize) {
+        let section = self.sections[section_id].clone();
+        let body = self.lines[section.first_line..section.end_line].to_vec();
+        for _ in 0..count {
+            self.lines.push(LyricLine {
+                text: format!("[{}]", section.name),
+                typed: false,
+            });
+            let heading_line = self.lines.len() - 1;
+            self.lines.extend(body.iter().cloned());
+            self.sections.push(Section {
+                name: section.name.clone(),
+                heading_line: Some(heading_line),
+                first_line: heading_line + 1,
+                end_line: self.lines.len(),
+            });
+        }
+    }
+
+    /// return lyrics that only contain the given section, to practice it
+    pub fn section_only(&self, section_id: usize) -> Self {
+        let section = &self.sections[section_id];
+        let mut lines = Vec::new();
+        if let Some(heading_line) = section.heading_line {
+            lines.push(self.lines[heading_line].clone());
+        };
+        let heading_offset = lines.len();
+        lines.extend_from_slice(&self.lines[section.first_line..section.end_line]);
+        Self {
+            sections: vec![Section {
+                name: section.name.clone(),
+                heading_line: section.heading_line.map(|_| 0),
+                first_line: heading_offset,
+                end_line: lines.len(),
+            }],
+            lines,
+        }
+    }
+
+    /// return the lyrics from the given line to the end, to start a song in the middle. The
+    /// sections that end before that line are removed, and the one it is in is cut.
+    pub fn starting_at_line(&self, first_line: usize) -> Self {
+        let first_line = first_line.min(self.lines.len());
+        Self {
+            lines: self.lines[first_line..].to_vec(),
+            sections: self
+                .sections
+                .iter()
+                .filter(|section| section.end_line > first_line)
+                .map(|section| Section {
+                    name: section.name.clone(),
+                    heading_line: section
+                        .heading_line
+                        .and_then(|heading_line| heading_line.checked_sub(first_line)),
+                    first_line: section.first_line.max(first_line) - first_line,
+                    end_line: section.end_line - first_line,
+                })
+                .collect(),
+        }
+    }
+
+    /// return the text of the lines, joined by new lines
+    pub fn text(&self) -> String {
+        self.lines
+            .iter()
+            .map(|line| line.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// split the text in lines, the last empty line being ignored
+fn split_lines(text: &str) -> impl Iterator<Item = &str> {
+    let ends_with_new_line = text.is_empty() || text.ends_with('\n');
+    let mut lines = text.split('\n').peekable();
+    std::iter::from_fn(move || {
+        let line = lines.next()?;
+        if lines.peek().is_none() && ends_with_new_line {
+            None
+        } else {
+            Some(line)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Lyrics, PlayingMusic, Section};
+
+    #[test]
+    fn test_lyrics_sections() {
+        let lyrics = Lyrics::parse("[Verse 1]\nhello\nworld\n\n[Chorus]\nla la\n\n(Chorus x2)\n[Verse 2]\nbye\nRepeat chorus\n");
+        let expected_text = "[Verse 1]\nhello\nworld\n\n[Chorus]\nla la\n\n[Chorus]\nla la\n[Chorus]\nla la\n[Verse 2]\nbye\n[Chorus]\nla la";
+        assert_eq!(lyrics.text(), expected_text);
+        let typed: Vec<bool> = lyrics.lines.iter().map(|line| line.typed).collect();
+        assert_eq!(
+            typed,
+            vec![
+                false, true, true, true, false, true, true, false, true, false, true, false, true,
+                false, true
+            ]
+        );
+        let sections: Vec<(&str, usize, usize)> = lyrics
+            .sections
+            .iter()
+            .map(|section| (section.name.as_str(), section.first_line, section.end_line))
+            .collect();
+        assert_eq!(
+            sections,
+            vec![
+                ("Verse 1", 1, 3),
+                ("Chorus", 5, 6),
+                ("Chorus", 8, 9),
+                ("Chorus", 10, 11),
+                ("Verse 2", 12, 13),
+                ("Chorus", 14, 15)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lyrics_empty_heading_repeat() {
+        let lyrics = Lyrics::parse("[Chorus]\nla\n[Verse]\nhey\n[Chorus]\n\n[Outro]\n(oh yeah)");
+        assert_eq!(
+            lyrics.text(),
+            "[Chorus]\nla\n[Verse]\nhey\n[Chorus]\nla\n\n[Outro]\n(oh yeah)"
+        );
+        assert_eq!(lyrics.sections.len(), 4);
+        assert!(lyrics.lines[8].typed);
+
+        let practice = lyrics.section_only(1);
+        assert_eq!(practice.text(), "[Verse]\nhey");
+        assert_eq!(
+            practice.sections,
+            vec![Section {
+                name: "Verse".into(),
+                heading_line: Some(0),
+                first_line: 1,
+                end_line: 2
+            }]
+        );
+    }
+
+    #[test]
+    fn test_lyrics_starting_at_line() {
+        let lyrics = Lyrics::parse("[Verse]\nhello\nworld\n[Chorus]\nla la");
+        let end = lyrics.starting_at_line(2);
+        assert_eq!(end.text(), "world\n[Chorus]\nla la");
+        assert_eq!(
+            end.sections,
+            vec![
+                Section {
+                    name: "Verse".into(),
+                    heading_line: None,
+                    first_line: 0,
+                    end_line: 1
+                },
+                Section {
+                    name: "Chorus".into(),
+                    heading_line: Some(1),
+                    first_line: 2,
+                    end_line: 3
+                }
+            ]
+        );
+        assert_eq!(lyrics.starting_at_line(3).sections.len(), 1);
+        assert!(lyrics.starting_at_line(10).lines.is_empty());
+    }
+
+    #[test]
+    fn test_playing_music_sections() {
+        let mut playing_music = PlayingMusic::new("[Verse]\nhello\n[Chorus]\nworld".into());
+        assert_eq!(playing_music.sections().len(), 2);
+        assert!(!playing_music.is_line_typed(0));
+        assert!(playing_music.is_line_typed(1));
+        playing_music.add_typed_text("hello");
+        assert_eq!(playing_music.current_section(), Some(1));
+        assert!((playing_music.section_correctness(0) - 1.0).abs() < 0.00000001);
+        assert!(playing_music.section_correctness(1).abs() < 0.00000001);
+        playing_music.add_typed_text(" wrld");
+        assert!((playing_music.correctness() - 10.0 / 11.0).abs() < 0.00000001);
+        assert!((playing_music.section_correctness(1) - 5.0 / 6.0).abs() < 0.00000001);
+    }
+}
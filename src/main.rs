@@ -1,263 +1,78 @@
-extern crate log;
+mod cli;
 
+#[cfg(feature = "gui")]
 use bevy::{
     diagnostic::{FrameTimeDiagnosticsPlugin, PrintDiagnosticsPlugin},
     prelude::*,
-    window::ReceivedCharacter,
+    window::{WindowDescriptor, WindowMode},
 };
-use music_typer::PlayingMusic;
-
-static TEST_SONG: &str = r#"
-Cutie Mark Crusaders, get out of my way
-Those ponies need to know the truth
-And they’ll hear it from me
-
-Stop! Diamond Tiara, this is not the way
-You know you’re better than this hostility
-
-You don’t even know me at all
-Don’t understand the meaning of my fall
-What my family would think if I ever
-Fail at anything
-
-I’m a diamond – that means you’ll never break
-No matter what be the cost of the path I take
-Whatever I have to do to win in the end
-
-Stop! This is not the answer
-Wait! And it’s plainly seen
-Listen! You can redeem yourself
-But by helping others, not by being mean
-
-We know you want friends who admire you
-You want to be the star with all the power too
-But there’s a better way, there’s a better wa-a-ay
-
-There’s so much more still left to
-Learn about yourself
-See the light that shines in you
-We know you can be somepony else
-
-You can stop right now
-And try another start
-You’ll finally free yourself from the dark
-And see the light
-And see the light of your cutie mark
-"#;
+#[cfg(feature = "gui")]
+use music_typer::{MusicTyperCommand, MusicTyperPlugin};
+use std::env;
+use std::process;
 
 fn main() {
-    env_logger::init();
-
-    App::build()
-        .add_plugins(DefaultPlugins)
-        .add_resource(OngoingMusic(None))
-        .add_resource(Fonts::default())
-        .add_resource(OngoingMusicDisplaySetting::default())
-        .add_resource(OngoingMusicDisplayData::default())
-        .add_startup_system(debug_spawn_ongoing_music.system())
-        .add_system(ongoing_music_input_system.system())
-        .add_system(debug_log.system())
-        .add_system(move_music_text_system.system())
-        .add_system(update_typed_text.system())
-        .add_plugin(FrameTimeDiagnosticsPlugin::default())
-        .add_plugin(PrintDiagnosticsPlugin::default())
-        .run();
-}
-
-struct OngoingMusicDisplayMarker;
-
-fn debug_spawn_ongoing_music(
-    commands: &mut Commands,
-    mut ongoing_music: ResMut<OngoingMusic>,
-    mut fonts: ResMut<Fonts>,
-    asset_server: Res<AssetServer>,
-) {
-    commands.spawn(UiCameraBundle::default());
-    fonts.ongoing_music_font = Some(asset_server.load("fonts/FiraSans-Bold.ttf"));
-    fonts.ongoing_music_font_size = 64.0;
-    ongoing_music.0 = Some(PlayingMusic::new(TEST_SONG.to_string()));
-    ongoing_music.spawn_text(commands, &*fonts);
-    commands
-        .spawn(TextBundle {
-            style: Style {
-                position_type: PositionType::Absolute,
-                position: Rect {
-                    right: Val::Percent(50.0),
-
-                    ..Default::default()
-                },
-                ..Default::default()
-            },
-            text: Text {
-                value: "".into(),
-                font: fonts
-                    .ongoing_music_font
-                    .clone()
-                    .expect("tried to use unitialized music font"),
-                style: TextStyle {
-                    font_size: fonts.ongoing_music_font_size,
-                    color: Color::YELLOW,
-                    ..Default::default()
-                },
-            },
-            ..Default::default()
-        })
-        .with(OngoingMusicDisplayMarker);
-}
-
-#[derive(Default)]
-struct Fonts {
-    ongoing_music_font: Option<Handle<Font>>,
-    ongoing_music_font_size: f32,
-}
-
-struct OngoingMusic(Option<PlayingMusic>);
-
-/// a line of a song, the usize it the line number
-struct MusicDisplayedLine(usize);
-
-//TODO: move this to a system that does stuff at OngoingMusic instanciation (also, automatically delete at uninstantiation)
-impl OngoingMusic {
-    fn spawn_text(&self, commands: &mut Commands, fonts: &Fonts) {
-        if let Some(playing_music) = self.0.as_ref() {
-            for (line_count, line) in playing_music.lines().iter().enumerate() {
-                commands
-                    .spawn(TextBundle {
-                        style: Style {
-                            position_type: PositionType::Absolute,
-                            ..Default::default()
-                        },
-                        text: Text {
-                            value: line.to_string(),
-                            font: fonts
-                                .ongoing_music_font
-                                .clone()
-                                .expect("tried to use unitialized music font"),
-                            style: TextStyle {
-                                font_size: 64.0,
-                                color: Color::WHITE,
-                                ..Default::default()
-                            },
-                        },
-                        ..Default::default()
-                    })
-                    .with(MusicDisplayedLine(line_count));
-            }
-        };
-    }
-}
-
-#[derive(Default)]
-struct State {
-    event_reader: EventReader<ReceivedCharacter>,
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("score") {
+        process::exit(cli::score(&args[1..]));
+    };
+    run_game(&args);
 }
 
-fn ongoing_music_input_system(
-    mut ongoing_music: ResMut<OngoingMusic>,
-    mut state: Local<State>,
-    char_input_events: Res<Events<ReceivedCharacter>>,
-) {
-    for event in state.event_reader.iter(&char_input_events) {
-        if let Some(music) = ongoing_music.0.as_mut() {
-            music.add_typed_char(event.char);
-        };
-    }
-}
-
-fn debug_log(ongoing_music: Res<OngoingMusic>) {
-    if let Some(playing_music) = ongoing_music.0.as_ref() {
-        println!("{:?}", playing_music.get_typed_text());
-        println!("{:?}", playing_music.correctness());
-
-        let typed_from_lyric = TEST_SONG
-            .chars()
-            .take(playing_music.position_in_source_text())
-            .collect::<String>();
-        println!("{}", typed_from_lyric);
-    }
-}
-
-struct OngoingMusicDisplaySetting {
-    current_color: Color,
-    non_current_color: Color,
-    distance_between_line: f32,
-    current_y: f32,
-    top_displayed_line: usize,
-    bottom_displayed_line: usize,
-}
-
-impl Default for OngoingMusicDisplaySetting {
-    fn default() -> Self {
-        Self {
-            current_color: Color::RED,
-            non_current_color: Color::WHITE,
-            distance_between_line: 100.0,
-            current_y: 1080.0 / 2.0, //TODO: compute at start and update on restart
-            top_displayed_line: 3,
-            bottom_displayed_line: 3,
+/// read the arguments of the game, or exit with the usage if they are wrong
+fn game_launch(args: &[String]) -> cli::GameLaunch {
+    match cli::game_launch(args) {
+        Ok(launch) => launch,
+        Err(err) => {
+            eprintln!(
+                "music_typer: {}\n{}\n{}",
+                err,
+                cli::GAME_USAGE,
+                cli::SCORE_USAGE
+            );
+            process::exit(cli::EXIT_ERROR);
         }
     }
 }
 
-struct OngoingMusicDisplayData {
-    actual_y_coordinate: f32,
-}
-
-impl Default for OngoingMusicDisplayData {
-    fn default() -> Self {
-        Self {
-            actual_y_coordinate: 0.0,
-        }
-    }
-}
+#[cfg(feature = "gui")]
+fn run_game(args: &[String]) {
+    let launch = game_launch(args);
+    env_logger::init();
 
-//TODO: only update what is required (put into multiple system and add an event ?)
-fn move_music_text_system(
-    ongoing_music: Res<OngoingMusic>,
-    ongoing_music_setting: Res<OngoingMusicDisplaySetting>,
-    mut ongoing_music_data: ResMut<OngoingMusicDisplayData>,
-    mut query: Query<(&MusicDisplayedLine, &mut Style, &mut Text, &mut Draw)>,
-) {
-    if let Some(playing_music) = &(*ongoing_music).0 {
-        let target_y_coordinate = ongoing_music_setting.current_y;
-        ongoing_music_data.actual_y_coordinate = target_y_coordinate;
-        let actual_line = playing_music.position_in_source_lines();
-        for (MusicDisplayedLine(line_count), mut style, mut text, mut draw) in query.iter_mut() {
-            let difference_isize: isize = *line_count as isize - actual_line as isize;
-            if difference_isize > ongoing_music_setting.bottom_displayed_line as isize
-                || difference_isize < -(ongoing_music_setting.top_displayed_line as isize)
-            {
-                draw.is_visible = false;
-                continue;
-            };
-            draw.is_visible = true;
-            let yet_to_be_typed = difference_isize >= 0;
-            let difference_f32 = difference_isize as f32;
-            if *line_count == actual_line {
-                text.style.color = ongoing_music_setting.current_color;
+    let fullscreen = launch.overrides.apply(&launch.settings).fullscreen;
+    let start = match (launch.song, launch.replay) {
+        (Some(entry), Some(replay)) => Some(MusicTyperCommand::Replay(entry, replay)),
+        (Some(entry), None) => Some(MusicTyperCommand::Start(entry)),
+        (None, _) => None,
+    };
+    App::build()
+        .add_resource(WindowDescriptor {
+            mode: if fullscreen {
+                WindowMode::BorderlessFullscreen
             } else {
-                text.style.color = ongoing_music_setting.non_current_color;
-            };
-            style.position.top = Val::Px(
-                (difference_f32 + if yet_to_be_typed { 1.0 } else { 0.0 })
-                    * ongoing_music_setting.distance_between_line
-                    + ongoing_music_data.actual_y_coordinate,
-            );
-        }
-    }
+                WindowMode::Windowed
+            },
+            ..Default::default()
+        })
+        .add_plugins(DefaultPlugins)
+        .add_plugin(MusicTyperPlugin {
+            settings: launch.settings,
+            overrides: launch.overrides,
+            settings_path: launch.settings_path,
+            start,
+            ..Default::default()
+        })
+        .add_plugin(FrameTimeDiagnosticsPlugin::default())
+        .add_plugin(PrintDiagnosticsPlugin::default())
+        .run();
 }
 
-fn update_typed_text(
-    ongoing_music: ChangedRes<OngoingMusic>,
-    ongoing_music_setting: Res<OngoingMusicDisplaySetting>,
-    mut query: Query<(&mut Text, &mut Style), With<OngoingMusicDisplayMarker>>,
-) {
-    if let Some(ongoing_music) = &ongoing_music.0 {
-        for (mut text, mut style) in query.iter_mut() {
-            let num_char_to_keep = 50;
-            let mut typed_text = ongoing_music.get_typed_text().to_string();
-            style.position.top = Val::Px(ongoing_music_setting.current_y);
-            text.value = typed_text.split_off(typed_text.len().saturating_sub(num_char_to_keep));
-        }
-    }
+/// the arguments are still checked, so that scripts get the same errors
+#[cfg(not(feature = "gui"))]
+fn run_game(args: &[String]) {
+    game_launch(args);
+    eprintln!("music_typer was built without the gui feature, only the score command is available");
+    eprintln!("{}", cli::SCORE_USAGE);
+    process::exit(cli::EXIT_ERROR);
 }
@@ -0,0 +1,23 @@
+use std::env;
+use std::path::PathBuf;
+
+/// the name of the directory of this application in the XDG directories
+const APPLICATION_DIRECTORY: &str = "music_typer";
+
+/// return $XDG_<variable>, or $HOME/<fallback> if it isn't set
+fn xdg_directory(variable: &str, fallback: &str) -> Option<PathBuf> {
+    match env::var_os(variable) {
+        Some(path) if !path.is_empty() => Some(PathBuf::from(path)),
+        _ => env::var_os("HOME").map(|home| PathBuf::from(home).join(fallback)),
+    }
+}
+
+/// the directory where the data of this application (like scores) are stored
+pub fn data_directory() -> Option<PathBuf> {
+    xdg_directory("XDG_DATA_HOME", ".local/share").map(|path| path.join(APPLICATION_DIRECTORY))
+}
+
+/// the directory where the settings of this application are stored
+pub fn config_directory() -> Option<PathBuf> {
+    xdg_directory("XDG_CONFIG_HOME", ".config").map(|path| path.join(APPLICATION_DIRECTORY))
+}
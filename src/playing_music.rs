@@ -1,8 +1,14 @@
+use crate::{align, align_window, AlignedChar, AlignmentStatus, DiffRun, Lyrics, Section};
 use crate::{DistanceIncremental, PositionIncremental};
+use crate::{FinishCriteria, FinishReason, Keystroke, KeystrokeKind, SessionResult, TypingStats};
 use log::error;
+use std::time::Duration;
 
+#[derive(Clone)]
 pub struct PlayingMusic {
     music_text_lines: Vec<String>,
+    typed_lines: Vec<bool>,
+    sections: Vec<Section>,
     score_source_text: String,
     score_source_text_len: usize,
     typed_text: String,
@@ -12,54 +18,87 @@ pub struct PlayingMusic {
     map_transformed_to_source: Vec<usize>,
     map_transformed_to_lines: Vec<usize>,
     precision: usize,
+    elapsed_time: Duration,
+    finish_criteria: FinishCriteria,
+    finished: Option<FinishReason>,
+    keystrokes: Vec<Keystroke>,
+    /// for every character of the typed text, its status when it was typed, with the number of
+    /// source characters it skipped (see live_counts)
+    typed_statuses: Vec<(AlignmentStatus, usize)>,
+}
+
+/// the number of source characters an alignment is made of
+fn alignment_source_len(alignment: &[AlignedChar]) -> usize {
+    alignment
+        .iter()
+        .filter(|aligned| aligned.source_index.is_some())
+        .count()
+}
+
+/// a 64 bit FNV-1a hash of the text, which is stable between versions and platforms
+pub fn song_hash(text: &str) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in text.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
 }
 
 static IGNORED_CHARACTERS: [char; 8] = ['\n', '.', ',', '?', '!', ';', ':', '\r'];
 
 impl PlayingMusic {
+    /// create a new PlayingMusic, with the section headings and the repeat references of the
+    /// text parsed (see Lyrics::parse)
     pub fn new(music_text: String) -> Self {
+        Self::from_lyrics(Lyrics::parse(&music_text))
+    }
+
+    pub fn from_lyrics(lyrics: Lyrics) -> Self {
         let mut music_text_fixed = String::new();
         let mut previous_char_was_space = false;
         let mut met_first_char = false;
         let mut map_transformed_to_source = Vec::new();
-        let mut music_text_lines = Vec::new();
-        let mut this_line = String::new();
         let mut map_transformed_to_lines = Vec::new();
-        let mut line_count = 0;
-        for (position, chara) in music_text.chars().enumerate() {
-            if chara == '\n' {
-                music_text_lines.push(this_line);
-                this_line = String::new();
-                line_count += 1;
-            } else {
-                this_line.push(chara);
+        let mut position = 0;
+        for (line_count, line) in lyrics.lines.iter().enumerate() {
+            if line_count != 0 {
+                // the new line character
+                previous_char_was_space = true;
+                position += 1;
             };
-            let chara = if IGNORED_CHARACTERS.contains(&chara) {
-                ' '
-            } else {
-                chara
+            if !line.typed {
+                position += line.text.chars().count();
+                continue;
             };
-            if chara == ' ' {
-                previous_char_was_space = true;
-            } else {
-                if previous_char_was_space && met_first_char {
-                    music_text_fixed.push(' ');
+            for chara in line.text.chars() {
+                let chara = if IGNORED_CHARACTERS.contains(&chara) {
+                    ' '
+                } else {
+                    chara
+                };
+                if chara == ' ' {
+                    previous_char_was_space = true;
+                } else {
+                    if previous_char_was_space && met_first_char {
+                        music_text_fixed.push(' ');
+                        map_transformed_to_source.push(position);
+                        map_transformed_to_lines.push(line_count);
+                    };
+                    met_first_char = true;
+                    music_text_fixed.push(chara);
+                    previous_char_was_space = false;
                     map_transformed_to_source.push(position);
                     map_transformed_to_lines.push(line_count);
-                };
-                met_first_char = true;
-                music_text_fixed.push(chara);
-                previous_char_was_space = false;
-                map_transformed_to_source.push(position);
-                map_transformed_to_lines.push(line_count);
+                }
+                position += 1;
             }
         }
-        if this_line.len() != 0 {
-            music_text_lines.push(this_line);
-        };
         let score_source_text_final = music_text_fixed.to_lowercase();
         Self {
-            music_text_lines,
+            typed_lines: lyrics.lines.iter().map(|line| line.typed).collect(),
+            music_text_lines: lyrics.lines.into_iter().map(|line| line.text).collect(),
+            sections: lyrics.sections,
             typed_text: String::new(),
             score_source_text_len: score_source_text_final.chars().count(),
             score_levenshtein: DistanceIncremental::new(&score_source_text_final),
@@ -69,6 +108,11 @@ impl PlayingMusic {
             map_transformed_to_source,
             map_transformed_to_lines,
             precision: 5,
+            elapsed_time: Duration::default(),
+            finish_criteria: FinishCriteria::default(),
+            finished: None,
+            keystrokes: Vec::new(),
+            typed_statuses: Vec::new(),
         }
     }
 
@@ -82,21 +126,80 @@ impl PlayingMusic {
         self.typed_text.push(chara);
         self.score_levenshtein.add_other_char(chara);
         self.position_levenshtein.add_other_char(chara);
+        self.push_typed_status();
     }
 
     fn push_str_for_score(&mut self, text: &str) {
-        self.typed_text.push_str(text);
-        self.score_levenshtein.add_other_str(text);
-        self.position_levenshtein.add_other_str(text);
+        for chara in text.chars() {
+            self.push_char_for_score(chara);
+        }
     }
 
     fn pop_char_for_score(&mut self) {
         self.typed_text.pop();
         self.score_levenshtein.pop_other_char();
         self.position_levenshtein.pop_other_char();
+        self.typed_statuses.pop();
+    }
+
+    /// record the status of the last typed character, from the alignment of the end of the
+    /// source text before the current position
+    fn push_typed_status(&mut self) {
+        let window_start = self
+            .position_in_transformed_text()
+            .saturating_sub(self.precision * 2 + 8);
+        let (alignment, typed_window_start) = self.live_alignment(window_start);
+        let last_typed = self.typed_text.chars().count() - 1 - typed_window_start;
+        let mut missing = 0;
+        let mut status = AlignmentStatus::Extra;
+        for aligned in alignment {
+            match aligned.typed_index {
+                Some(typed_index) if typed_index == last_typed => {
+                    status = aligned.status;
+                    break;
+                }
+                Some(_) => missing = 0,
+                None if aligned.status == AlignmentStatus::Missing => missing += 1,
+                None => (),
+            };
+        }
+        self.typed_statuses.push((status, missing));
+    }
+
+    /// the number of correct typed characters, and of uncorrected errors, as they were when
+    /// every character was typed. It is cheap enough to be computed every frame, but a later
+    /// character can change how the previous ones are aligned, so it can differ a bit from the
+    /// counts of the full alignment.
+    pub fn live_counts(&self) -> (usize, usize) {
+        let mut correct_char_count = 0;
+        let mut uncorrected_error_count = 0;
+        for (status, missing) in &self.typed_statuses {
+            if *status == AlignmentStatus::Correct {
+                correct_char_count += 1;
+            } else {
+                uncorrected_error_count += 1;
+            };
+            uncorrected_error_count += missing;
+        }
+        (correct_char_count, uncorrected_error_count)
     }
 
     pub fn add_typed_char(&mut self, chara: char) {
+        if self.finished.is_some() {
+            return;
+        };
+        let kind = if chara == '\u{8}' {
+            KeystrokeKind::Backspace
+        } else if chara == ' ' || IGNORED_CHARACTERS.contains(&chara) {
+            KeystrokeKind::Separator
+        } else {
+            KeystrokeKind::Character
+        };
+        self.keystrokes.push(Keystroke {
+            time: self.elapsed_time,
+            chara,
+            kind,
+        });
         if chara == '\u{8}' {
             if self.previous_character_was_space {
                 self.previous_character_was_space = false;
@@ -120,12 +223,58 @@ impl PlayingMusic {
                 self.previous_character_was_space = false;
             };
         }
+        if self.finish_criteria.end_of_text && self.is_at_end() {
+            self.finished = Some(FinishReason::EndOfText);
+        };
     }
 
     pub fn get_typed_text(&self) -> &str {
         self.typed_text.as_str()
     }
 
+    /// return true if a space was typed, but not added to the typed text yet. Consecutive
+    /// spaces and ignored characters are collapsed in a single space, which is only added with
+    /// the next character.
+    pub fn has_pending_space(&self) -> bool {
+        self.previous_character_was_space
+    }
+
+    /// for every character of the typed text from typed_start (in char), return true if the
+    /// aligner consider it wrong (substituted or extra). Only the end of the source text, around
+    /// the current position, is aligned, so it stays cheap for a short end of the typed text.
+    pub fn typed_mistakes(&self, typed_start: usize) -> Vec<bool> {
+        let typed: Vec<char> = self.typed_text.chars().collect();
+        let typed_start = typed_start.min(typed.len());
+        let tail_len = typed.len() - typed_start;
+        let position = self.position_in_transformed_text();
+        let aligned_end = (position + self.precision * 2 + 8).min(self.score_source_text_len);
+        let window_start = position.saturating_sub(tail_len * 2 + 16);
+        let source: Vec<char> = self
+            .score_source_text
+            .chars()
+            .skip(window_start)
+            .take(aligned_end - window_start)
+            .collect();
+        let typed_window_start = typed
+            .len()
+            .saturating_sub(source.len() * 2 + 16)
+            .min(typed_start);
+        let mut mistakes = vec![false; tail_len];
+        for aligned in align_window(&source, &typed[typed_window_start..]) {
+            let wrong = matches!(
+                aligned.status,
+                AlignmentStatus::Substituted | AlignmentStatus::Extra
+            );
+            if let Some(typed_index) = aligned.typed_index {
+                let typed_index = typed_window_start + typed_index;
+                if typed_index >= typed_start {
+                    mistakes[typed_index - typed_start] = wrong;
+                };
+            };
+        }
+        mistakes
+    }
+
     pub fn correctness(&self) -> f64 {
         let number_of_required_change = self.score_levenshtein.distance();
         let number_of_maximal_change = self.score_source_text_len as u32;
@@ -138,10 +287,32 @@ impl PlayingMusic {
         (number_of_valid_character as f64) / (number_of_maximal_change as f64)
     }
 
+    pub fn distance(&self) -> u32 {
+        self.score_levenshtein.distance()
+    }
+
+    /// return true if the position tracker reached the end of the source text
+    pub fn is_at_end(&self) -> bool {
+        self.position_levenshtein.position(self.precision) >= self.score_source_text_len
+    }
+
+    /// the position in the transformed source text (in lowercase, and with ignored characters
+    /// removed), which is more appropriate than the other positions to compare progress
+    pub fn position_in_transformed_text(&self) -> usize {
+        self.position_levenshtein
+            .position(self.precision)
+            .min(self.score_source_text_len)
+    }
+
     pub fn position_in_source_text(&self) -> usize {
         let transformed_position = self.position_levenshtein.position(self.precision);
         if let Some(position) = self.map_transformed_to_source.get(transformed_position) {
             *position
+        } else if transformed_position == self.score_source_text_len {
+            self.map_transformed_to_source
+                .last()
+                .map(|position| position + 1)
+                .unwrap_or(0)
         } else {
             error!("the computed position is out of the source text");
             self.score_source_text.chars().count()
@@ -152,20 +323,387 @@ impl PlayingMusic {
         let transformed_position = self.position_levenshtein.position(self.precision);
         if let Some(position) = self.map_transformed_to_lines.get(transformed_position) {
             *position
+        } else if transformed_position == self.score_source_text_len {
+            self.map_transformed_to_lines.last().copied().unwrap_or(0)
         } else {
             error!("the computer position is out the source lines");
             self.music_text_lines.len()
         }
     }
 
+    /// the position in the line given by position_in_source_lines, in characters
+    pub fn position_in_source_line(&self) -> usize {
+        let line = self.position_in_source_lines();
+        let line_start: usize = self
+            .music_text_lines
+            .iter()
+            .take(line)
+            .map(|line| line.chars().count() + 1)
+            .sum();
+        self.position_in_source_text().saturating_sub(line_start)
+    }
+
+    /// forget everything that was typed, to start the song again
+    pub fn restart(&mut self) {
+        self.typed_text = String::new();
+        self.previous_character_was_space = false;
+        self.score_levenshtein = DistanceIncremental::new(&self.score_source_text);
+        self.position_levenshtein = PositionIncremental::new(&self.score_source_text);
+        self.elapsed_time = Duration::default();
+        self.finished = None;
+        self.keystrokes = Vec::new();
+        self.typed_statuses = Vec::new();
+    }
+
+    /// a hash of the source text, that identify this song
+    pub fn song_hash(&self) -> u64 {
+        song_hash(&self.source_text())
+    }
+
+    /// the precision used to compute the position in the text (the default is 5)
+    pub fn precision(&self) -> usize {
+        self.precision
+    }
+
+    pub fn set_precision(&mut self, precision: usize) {
+        self.precision = precision;
+    }
+
+    pub fn finish_criteria(&self) -> &FinishCriteria {
+        &self.finish_criteria
+    }
+
+    pub fn set_finish_criteria(&mut self, finish_criteria: FinishCriteria) {
+        self.finish_criteria = finish_criteria;
+    }
+
+    /// set the time elapsed since the start of the song. The time doesn't change once the song
+    /// is finished.
+    pub fn set_elapsed_time(&mut self, elapsed_time: Duration) {
+        if self.finished.is_some() {
+            return;
+        };
+        self.elapsed_time = elapsed_time;
+        if let Some(time_limit) = self.finish_criteria.time_limit {
+            if self.elapsed_time >= time_limit {
+                self.elapsed_time = time_limit;
+                self.finished = Some(FinishReason::TimeLimit);
+            };
+        };
+    }
+
+    pub fn elapsed_time(&self) -> Duration {
+        self.elapsed_time
+    }
+
+    /// explicitly finish the song
+    pub fn finish(&mut self) {
+        if self.finished.is_none() {
+            self.finished = Some(FinishReason::Explicit);
+        };
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished.is_some()
+    }
+
+    pub fn finish_reason(&self) -> Option<FinishReason> {
+        self.finished
+    }
+
+    /// return the result of this song, or None if it isn't finished yet
+    pub fn result(&self) -> Option<SessionResult> {
+        let finish_reason = self.finished?;
+        Some(SessionResult {
+            finish_reason,
+            correctness: self.correctness(),
+            distance: self.distance(),
+            duration: self.elapsed_time,
+            source_char_count: self.score_source_text_len,
+            typed_char_count: self.typed_text.chars().count(),
+            keystroke_count: self.keystrokes.len(),
+            backspace_count: self
+                .keystrokes
+                .iter()
+                .filter(|keystroke| keystroke.kind == KeystrokeKind::Backspace)
+                .count(),
+            position_in_source_text: self.position_in_source_text(),
+        })
+    }
+
+    /// every key pressed since the start of the song, with the time they were pressed at
+    pub fn keystrokes(&self) -> &[Keystroke] {
+        &self.keystrokes
+    }
+
+    pub fn stats(&self) -> TypingStats {
+        TypingStats::compute(self)
+    }
+
     pub fn lines(&self) -> &Vec<String> {
         &self.music_text_lines
     }
+
+    /// return the lines joined by new lines. position_in_source_text is an index in this text.
+    pub fn source_text(&self) -> String {
+        self.music_text_lines.join("\n")
+    }
+
+    /// return false if the line is only displayed, like a section heading
+    pub fn is_line_typed(&self, line: usize) -> bool {
+        self.typed_lines.get(line).copied().unwrap_or(false)
+    }
+
+    pub fn sections(&self) -> &[Section] {
+        &self.sections
+    }
+
+    /// return the index of the section that contain the current position
+    pub fn current_section(&self) -> Option<usize> {
+        let line = self.position_in_source_lines();
+        self.sections
+            .iter()
+            .position(|section| section.first_line <= line && line < section.end_line)
+    }
+
+    /// align the typed text with the transformed source text (see the align function)
+    pub fn alignment(&self, finished: bool) -> Vec<AlignedChar> {
+        let source: Vec<char> = self.score_source_text.chars().collect();
+        let typed: Vec<char> = self.typed_text.chars().collect();
+        align(&source, &typed, finished)
+    }
+
+    /// The alignment of the typed text, projected on the source lines: every line is split in
+    /// runs of characters with the same status. The status of a transformed space is given to
+    /// the spaces and ignored characters it replace, and extra typed characters are inserted
+    /// after the source character they follow.
+    pub fn source_diff(&self, finished: bool) -> Vec<Vec<DiffRun>> {
+        let typed: Vec<char> = self.typed_text.chars().collect();
+        self.project_alignment(
+            &self.alignment(finished),
+            0,
+            &typed,
+            0,
+            self.music_text_lines.len(),
+        )
+    }
+
+    /// Like source_diff, but only for the lines from first_line to end_line (excluded), and
+    /// cheap enough to be computed after every keystroke. Only the part of the source text from
+    /// the start of first_line to a bit after the current position is aligned, with the end of
+    /// the typed text, and the rest is Untyped.
+    pub fn live_diff(&self, first_line: usize, end_line: usize) -> Vec<Vec<DiffRun>> {
+        let end_line = end_line.min(self.music_text_lines.len());
+        if first_line >= end_line {
+            return Vec::new();
+        };
+        let position = self.position_in_transformed_text();
+        let first_in_lines = |line: usize| {
+            self.map_transformed_to_lines
+                .iter()
+                .position(|transformed_line| *transformed_line >= line)
+                .unwrap_or(self.score_source_text_len)
+        };
+        let window_start = first_in_lines(first_line).min(position);
+        let (mut alignment, typed_window_start) = self.live_alignment(window_start);
+        let aligned_end = window_start + alignment_source_len(&alignment);
+        let window_end = first_in_lines(end_line).max(aligned_end);
+        alignment.extend(
+            (aligned_end - window_start..window_end - window_start).map(|index| AlignedChar {
+                status: AlignmentStatus::Untyped,
+                source_index: Some(index),
+                typed_index: None,
+            }),
+        );
+        let typed: Vec<char> = self.typed_text.chars().collect();
+        self.project_alignment(
+            &alignment,
+            window_start,
+            &typed[typed_window_start..],
+            first_line,
+            end_line,
+        )
+    }
+
+    /// Align the transformed source text, from window_start to a bit after the current
+    /// position, with the end of the typed text. The source characters after the last typed
+    /// one are Untyped. Return the alignment, whose source index start at window_start, and the
+    /// index of the first typed character it was aligned with.
+    pub(crate) fn live_alignment(&self, window_start: usize) -> (Vec<AlignedChar>, usize) {
+        let position = self.position_in_transformed_text();
+        // the position tracker can be a few characters behind the typed text
+        let aligned_end = (position + self.precision * 2 + 8).min(self.score_source_text_len);
+        let window_start = window_start.min(aligned_end);
+        let source: Vec<char> = self
+            .score_source_text
+            .chars()
+            .skip(window_start)
+            .take(aligned_end - window_start)
+            .collect();
+        let typed: Vec<char> = self.typed_text.chars().collect();
+        // enough typed characters to cover the window, even with many mistakes
+        let typed_window_len = typed.len().min(source.len() * 2 + 16);
+        let typed_window_start = typed.len() - typed_window_len;
+        let mut alignment = align_window(&source, &typed[typed_window_start..]);
+        for aligned in alignment.iter_mut().rev() {
+            if aligned.status != AlignmentStatus::Missing {
+                break;
+            };
+            aligned.status = AlignmentStatus::Untyped;
+        }
+        (alignment, typed_window_start)
+    }
+
+    /// the transformed source text: in lowercase, with the ignored characters and the
+    /// consecutive spaces replaced by a single space
+    pub(crate) fn transformed_text(&self) -> &str {
+        &self.score_source_text
+    }
+
+    /// the source line of the given character of the transformed text
+    pub(crate) fn transformed_line(&self, transformed_index: usize) -> Option<usize> {
+        self.map_transformed_to_lines
+            .get(transformed_index)
+            .copied()
+    }
+
+    /// project an alignment on the lines from first_line to end_line (excluded), as described
+    /// in source_diff. The source index of the alignment start at transformed_start, and its
+    /// typed index are index in typed.
+    fn project_alignment(
+        &self,
+        alignment: &[AlignedChar],
+        transformed_start: usize,
+        typed: &[char],
+        first_line: usize,
+        end_line: usize,
+    ) -> Vec<Vec<DiffRun>> {
+        let source_start: usize = self.music_text_lines[..first_line]
+            .iter()
+            .map(|line| line.chars().count() + 1)
+            .sum();
+        let source: Vec<char> = self.music_text_lines[first_line..end_line]
+            .join("\n")
+            .chars()
+            .collect();
+        let transformed: Vec<char> = self.score_source_text.chars().collect();
+        let mut statuses: Vec<Option<AlignmentStatus>> = vec![None; source.len()];
+        // the extra typed characters placed before every source character
+        let mut extras: Vec<String> = vec![String::new(); source.len() + 1];
+        let mut source_end = 0;
+        for aligned in alignment {
+            let transformed_index = match aligned.source_index {
+                Some(source_index) => transformed_start + source_index,
+                None => {
+                    if let Some(chara) = aligned.typed_index.and_then(|index| typed.get(index)) {
+                        extras[source_end].push(*chara);
+                    };
+                    continue;
+                }
+            };
+            let position = match self.map_transformed_to_source.get(transformed_index) {
+                Some(position) if *position >= source_start => *position - source_start,
+                _ => continue,
+            };
+            if position >= source.len() {
+                continue;
+            };
+            if transformed.get(transformed_index) == Some(&' ') {
+                for index in source_end..position {
+                    if source[index] != '\n' {
+                        statuses[index] = Some(aligned.status);
+                    };
+                }
+            } else {
+                statuses[position] = Some(aligned.status);
+                source_end = position + 1;
+            };
+        }
+
+        let mut lines = vec![Vec::new()];
+        let push = |lines: &mut Vec<Vec<DiffRun>>, status, chara| {
+            let line = lines.last_mut().unwrap();
+            match line.last_mut() {
+                Some(DiffRun {
+                    status: run_status,
+                    text,
+                }) if *run_status == status => text.push(chara),
+                _ => line.push(DiffRun {
+                    status,
+                    text: chara.to_string(),
+                }),
+            };
+        };
+        for (index, chara) in source.iter().enumerate() {
+            for extra in extras[index].chars() {
+                push(&mut lines, Some(AlignmentStatus::Extra), extra);
+            }
+            if *chara == '\n' {
+                lines.push(Vec::new());
+            } else {
+                push(&mut lines, statuses[index], *chara);
+            };
+        }
+        for extra in extras[source.len()].chars() {
+            push(&mut lines, Some(AlignmentStatus::Extra), extra);
+        }
+        lines
+    }
+
+    /// the correctness of a single section, computed like correctness, where extra typed
+    /// characters are counted in the section of the previous source character. Like
+    /// live_diff, only the source text from the start of the section to the current position is
+    /// aligned, with the end of the typed text, so it is meant to be called when the section
+    /// was just typed.
+    pub fn section_correctness(&self, section_id: usize) -> f64 {
+        let section = match self.sections.get(section_id) {
+            Some(section) => section,
+            None => return 0.0,
+        };
+        let in_section = |source_index: usize| {
+            let line = self.map_transformed_to_lines[source_index];
+            section.first_line <= line && line < section.end_line
+        };
+        let section_len = (0..self.score_source_text_len)
+            .filter(|source_index| in_section(*source_index))
+            .count();
+        if section_len == 0 {
+            return 1.0;
+        };
+        let window_start = (0..self.score_source_text_len)
+            .find(|source_index| in_section(*source_index))
+            .unwrap_or(self.score_source_text_len);
+        // nothing of the section was typed yet
+        if self.position_in_transformed_text() <= window_start {
+            return 0.0;
+        };
+        let (alignment, _) = self.live_alignment(window_start);
+        let mut valid_character: usize = 0;
+        let mut extra_character = 0;
+        let mut previous_in_section = false;
+        for aligned in alignment {
+            match aligned.source_index {
+                Some(source_index) => {
+                    previous_in_section = in_section(window_start + source_index);
+                    if previous_in_section && aligned.status == AlignmentStatus::Correct {
+                        valid_character += 1;
+                    };
+                }
+                None => {
+                    if previous_in_section {
+                        extra_character += 1;
+                    }
+                }
+            }
+        }
+        (valid_character.saturating_sub(extra_character) as f64) / (section_len as f64)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::PlayingMusic;
+    use crate::{FinishCriteria, FinishReason, PlayingMusic};
+    use std::time::Duration;
 
     #[test]
     fn test_playing_music() {
@@ -178,6 +716,46 @@ mod tests {
         assert!((playing_music.correctness() - 1.0) < 0.00000001);
     }
 
+    #[test]
+    fn test_playing_music_finish() {
+        let mut playing_music = PlayingMusic::new("hello world".to_string());
+        playing_music.set_elapsed_time(Duration::from_secs(2));
+        playing_music.add_typed_text("hello");
+        assert!(!playing_music.is_finished());
+        assert_eq!(playing_music.result(), None);
+        playing_music.add_typed_text(" worlf\u{8}d");
+        assert!(playing_music.is_finished());
+        playing_music.set_elapsed_time(Duration::from_secs(3));
+        playing_music.add_typed_char('s');
+        let result = playing_music.result().unwrap();
+        assert_eq!(result.finish_reason, FinishReason::EndOfText);
+        assert_eq!(result.duration, Duration::from_secs(2));
+        assert_eq!(result.distance, 0);
+        assert_eq!(result.typed_char_count, 11);
+        assert_eq!(result.keystroke_count, 13);
+        assert_eq!(result.backspace_count, 1);
+        assert_eq!(result.position_in_source_text, 11);
+
+        let mut playing_music = PlayingMusic::new("hello world".to_string());
+        playing_music.set_finish_criteria(FinishCriteria {
+            end_of_text: false,
+            time_limit: Some(Duration::from_secs(10)),
+        });
+        playing_music.add_typed_text("hello world");
+        assert!(!playing_music.is_finished());
+        playing_music.set_elapsed_time(Duration::from_secs(11));
+        let result = playing_music.result().unwrap();
+        assert_eq!(result.finish_reason, FinishReason::TimeLimit);
+        assert_eq!(result.duration, Duration::from_secs(10));
+
+        let mut playing_music = PlayingMusic::new("hello world".to_string());
+        playing_music.finish();
+        assert_eq!(
+            playing_music.result().unwrap().finish_reason,
+            FinishReason::Explicit
+        );
+    }
+
     #[test]
     fn test_playing_music_lines() {
         let playing_music = PlayingMusic::new("h\ne\n\nl\nlo".into());
@@ -188,5 +766,53 @@ mod tests {
             .zip(expected.iter())
             .map(|(music_line, expected_line)| assert_eq!(music_line, expected_line))
             .collect::<Vec<_>>();
+
+        let mut playing_music = PlayingMusic::new("hello\nworld, this is".into());
+        playing_music.add_typed_text("hello world, th");
+        assert_eq!(playing_music.position_in_source_lines(), 1);
+        assert_eq!(playing_music.position_in_source_line(), 9);
+    }
+
+    #[test]
+    fn test_playing_music_source_diff() {
+        use crate::{AlignmentStatus::*, DiffRun};
+        let run = |status, text: &str| DiffRun {
+            status,
+            text: text.to_string(),
+        };
+        let mut playing_music = PlayingMusic::new("[Verse]\nHi, you\nok".into());
+        playing_music.add_typed_text("hi yxou");
+        assert_eq!(
+            playing_music.source_diff(false),
+            vec![
+                vec![run(None, "[Verse]")],
+                vec![
+                    run(Some(Correct), "Hi, y"),
+                    run(Some(Extra), "x"),
+                    run(Some(Correct), "ou")
+                ],
+                vec![run(Some(Untyped), "ok")],
+            ]
+        );
+        playing_music.add_typed_text(" o");
+        playing_music.finish();
+        assert_eq!(
+            playing_music.source_diff(true)[2],
+            vec![run(Some(Correct), "o"), run(Some(Missing), "k")]
+        );
+
+        let mut playing_music = PlayingMusic::new("one two\nthree\nfour five".into());
+        playing_music.add_typed_text("one two threx fo");
+        assert_eq!(
+            playing_music.live_diff(1, 3),
+            vec![
+                vec![run(Some(Correct), "thre"), run(Some(Substituted), "e")],
+                vec![run(Some(Correct), "fo"), run(Some(Untyped), "ur five")],
+            ]
+        );
+        assert_eq!(
+            playing_music.live_diff(1, 3),
+            playing_music.source_diff(false)[1..3].to_vec()
+        );
     }
 }
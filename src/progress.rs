@@ -0,0 +1,281 @@
+use crate::{AlignmentStatus, PlayingMusic};
+use std::ops::Range;
+
+/// a milestone reached while typing a song
+#[derive(Debug, PartialEq, Clone)]
+pub enum ProgressEvent {
+    /// a character was added to the typed text, correct is false if the aligner consider it
+    /// wrong
+    CharTyped { correct: bool },
+    /// the player typed past a word of the transformed text. errors is the number of its
+    /// characters that are substituted or missing, plus the extra characters typed in it.
+    WordCompleted { word: String, errors: usize },
+    /// every word of a source line was completed. accuracy is the part of its characters that
+    /// were typed correctly, extra characters counting as typed wrong.
+    LineCompleted { line: usize, accuracy: f64 },
+    /// every line of a section was completed
+    SectionCompleted { section: usize, correctness: f64 },
+    /// a wrong character ended a series of combo correct characters
+    ComboBroken { combo: usize },
+}
+
+/// the number of characters aligned before the first word that isn't completed
+const ALIGNMENT_CONTEXT: usize = 32;
+
+/// a word of the transformed text
+#[derive(Debug, Clone)]
+struct Word {
+    /// the index of its characters in the transformed text
+    chars: Range<usize>,
+    /// the source line it is on
+    line: usize,
+}
+
+/// follow the typing of a PlayingMusic to report its progress as events. The events are
+/// derived from the alignment of the typed text, and every word, line and section is only
+/// reported once, so that erasing and typing again doesn't report them again.
+#[derive(Debug, Clone)]
+pub struct ProgressTracker {
+    words: Vec<Word>,
+    /// the number of words already completed
+    completed_words: usize,
+    /// the number of correct characters and of characters of the line of the last completed
+    /// word
+    line_correct: usize,
+    line_total: usize,
+    /// the typed text when it was last updated
+    typed_text: String,
+    combo: usize,
+}
+
+impl ProgressTracker {
+    pub fn new(playing_music: &PlayingMusic) -> Self {
+        let mut words = Vec::new();
+        let mut word_start = None;
+        let transformed: Vec<char> = playing_music.transformed_text().chars().collect();
+        for (index, chara) in transformed.iter().enumerate() {
+            match (word_start, *chara == ' ') {
+                (None, false) => word_start = Some(index),
+                (Some(start), true) => {
+                    words.push(Word {
+                        chars: start..index,
+                        line: playing_music.transformed_line(start).unwrap_or(0),
+                    });
+                    word_start = None;
+                }
+                _ => (),
+            };
+        }
+        if let Some(start) = word_start {
+            words.push(Word {
+                chars: start..transformed.len(),
+                line: playing_music.transformed_line(start).unwrap_or(0),
+            });
+        };
+        Self {
+            words,
+            completed_words: 0,
+            line_correct: 0,
+            line_total: 0,
+            typed_text: playing_music.get_typed_text().to_string(),
+            combo: 0,
+        }
+    }
+
+    /// the number of correct characters typed in a row
+    pub fn combo(&self) -> usize {
+        self.combo
+    }
+
+    /// return the events that happened since the last update
+    pub fn update(&mut self, playing_music: &PlayingMusic) -> Vec<ProgressEvent> {
+        let mut events = Vec::new();
+        let typed_text = playing_music.get_typed_text();
+        // the characters typed since the last update, even if some were erased before
+        let kept_len = self
+            .typed_text
+            .chars()
+            .zip(typed_text.chars())
+            .take_while(|(previous, actual)| previous == actual)
+            .count();
+        if typed_text.chars().count() > kept_len {
+            for mistake in playing_music.typed_mistakes(kept_len) {
+                events.push(ProgressEvent::CharTyped { correct: !mistake });
+                if !mistake {
+                    self.combo += 1;
+                } else if self.combo > 0 {
+                    events.push(ProgressEvent::ComboBroken { combo: self.combo });
+                    self.combo = 0;
+                };
+            }
+        };
+        self.typed_text = typed_text.to_string();
+        self.complete_words(playing_music, &mut events);
+        events
+    }
+
+    fn complete_words(&mut self, playing_music: &PlayingMusic, events: &mut Vec<ProgressEvent>) {
+        let window_start = match self.words.get(self.completed_words) {
+            // the text before the word is aligned too, so that the end of the typed text isn't
+            // aligned with the word only because it is the start of the window
+            Some(word) => word.chars.start.saturating_sub(ALIGNMENT_CONTEXT),
+            None => return,
+        };
+        let (alignment, _) = playing_music.live_alignment(window_start);
+        // the end of the part of the transformed text the typed text reach
+        let mut reach = window_start;
+        for aligned in &alignment {
+            match (aligned.source_index, aligned.status) {
+                (Some(source_index), AlignmentStatus::Correct)
+                | (Some(source_index), AlignmentStatus::Substituted) => {
+                    reach = window_start + source_index + 1
+                }
+                _ => (),
+            };
+        }
+        if playing_music.has_pending_space() {
+            reach += 1;
+        };
+        let transformed: Vec<char> = playing_music.transformed_text().chars().collect();
+        while let Some(word) = self.words.get(self.completed_words).cloned() {
+            let is_last = self.completed_words + 1 == self.words.len();
+            // a word is completed once the space after it is typed, or its last character for
+            // the last word
+            let completed = reach > word.chars.end
+                || (is_last && (reach >= word.chars.end || playing_music.is_at_end()));
+            if !completed {
+                break;
+            };
+            let mut errors = 0;
+            let mut extras = 0;
+            let mut in_word = false;
+            for aligned in &alignment {
+                match aligned.source_index {
+                    Some(source_index) => {
+                        in_word = word.chars.contains(&(window_start + source_index));
+                        if in_word && aligned.status != AlignmentStatus::Correct {
+                            errors += 1;
+                        };
+                    }
+                    None => {
+                        if in_word {
+                            extras += 1;
+                        };
+                    }
+                };
+            }
+            let word_len = word.chars.len();
+            self.line_correct += word_len.saturating_sub(errors);
+            self.line_total += word_len + extras;
+            events.push(ProgressEvent::WordCompleted {
+                word: transformed[word.chars.clone()].iter().collect(),
+                errors: errors + extras,
+            });
+            self.completed_words += 1;
+
+            let next_line = self.words.get(self.completed_words).map(|next| next.line);
+            if next_line != Some(word.line) {
+                let accuracy = if self.line_total == 0 {
+                    1.0
+                } else {
+                    self.line_correct as f64 / self.line_total as f64
+                };
+                events.push(ProgressEvent::LineCompleted {
+                    line: word.line,
+                    accuracy,
+                });
+                self.line_correct = 0;
+                self.line_total = 0;
+                let section = playing_music.sections().iter().position(|section| {
+                    section.first_line <= word.line && word.line < section.end_line
+                });
+                if let Some(section_id) = section {
+                    let end_line = playing_music.sections()[section_id].end_line;
+                    if next_line
+                        .filter(|next_line| *next_line < end_line)
+                        .is_none()
+                    {
+                        events.push(ProgressEvent::SectionCompleted {
+                            section: section_id,
+                            correctness: playing_music.section_correctness(section_id),
+                        });
+                    };
+                };
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{PlayingMusic, ProgressEvent, ProgressTracker};
+
+    #[test]
+    fn test_progress_tracker() {
+        let mut playing_music = PlayingMusic::new("hello world\nsecond line".into());
+        let mut tracker = ProgressTracker::new(&playing_music);
+        playing_music.add_typed_text("hello");
+        let events = tracker.update(&playing_music);
+        assert_eq!(events.len(), 5);
+        assert!(events
+            .iter()
+            .all(|event| *event == ProgressEvent::CharTyped { correct: true }));
+        assert_eq!(tracker.combo(), 5);
+
+        playing_music.add_typed_text(" wprld");
+        let events = tracker.update(&playing_music);
+        assert_eq!(
+            events[0..2],
+            [
+                ProgressEvent::CharTyped { correct: true },
+                ProgressEvent::CharTyped { correct: true },
+            ]
+        );
+        assert!(events.contains(&ProgressEvent::WordCompleted {
+            word: "hello".into(),
+            errors: 0
+        }));
+        assert!(events.contains(&ProgressEvent::ComboBroken { combo: 7 }));
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, ProgressEvent::LineCompleted { .. })));
+
+        playing_music.add_typed_text("\u{8}\u{8}\u{8}\u{8}orld ");
+        let events = tracker.update(&playing_music);
+        assert_eq!(
+            events[0..4],
+            [
+                ProgressEvent::CharTyped { correct: true },
+                ProgressEvent::CharTyped { correct: true },
+                ProgressEvent::CharTyped { correct: true },
+                ProgressEvent::CharTyped { correct: true },
+            ]
+        );
+        assert!(events.contains(&ProgressEvent::WordCompleted {
+            word: "world".into(),
+            errors: 0
+        }));
+        assert!(events.contains(&ProgressEvent::LineCompleted {
+            line: 0,
+            accuracy: 1.0
+        }));
+
+        // erasing and typing the end of the word again doesn't complete it twice, and only the
+        // characters that differ from the previous typed text are reported
+        playing_music.add_typed_text("\u{8}\u{8}\u{8}ld s");
+        let events = tracker.update(&playing_music);
+        assert_eq!(events.len(), 2);
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, ProgressEvent::WordCompleted { .. })));
+
+        playing_music.add_typed_text("econd lin");
+        tracker.update(&playing_music);
+        playing_music.add_typed_text("e");
+        let events = tracker.update(&playing_music);
+        assert!(events.contains(&ProgressEvent::LineCompleted {
+            line: 1,
+            accuracy: 1.0
+        }));
+    }
+}
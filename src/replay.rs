@@ -0,0 +1,252 @@
+use crate::{paths, FinishCriteria, FinishReason, PlayingMusic};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// the current version of the replay format
+const REPLAY_VERSION: u32 = 1;
+
+/// the settings of the PlayingMusic that change how the typed text is processed
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ReplaySettings {
+    pub precision: usize,
+    pub finish_criteria: FinishCriteria,
+}
+
+/// a key pressed during a replay. time is in microseconds since the start of the song.
+#[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
+pub struct ReplayKeystroke {
+    pub time: u64,
+    pub chara: char,
+}
+
+impl ReplayKeystroke {
+    pub fn duration(&self) -> Duration {
+        Duration::from_micros(self.time)
+    }
+}
+
+/// a recorded run of a song, that can be played again with a ReplayPlayer
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    /// a human readable identifier of the song, like its title
+    pub song_id: String,
+    /// the hash of the source text of the song (see PlayingMusic::song_hash)
+    pub song_hash: u64,
+    pub settings: ReplaySettings,
+    pub keystrokes: Vec<ReplayKeystroke>,
+    /// the duration of the run, in microseconds
+    pub duration: u64,
+    pub finish_reason: Option<FinishReason>,
+}
+
+impl Replay {
+    /// record the run of the given PlayingMusic
+    pub fn from_playing_music(playing_music: &PlayingMusic, song_id: String) -> Self {
+        Self {
+            version: REPLAY_VERSION,
+            song_id,
+            song_hash: playing_music.song_hash(),
+            settings: ReplaySettings {
+                precision: playing_music.precision(),
+                finish_criteria: playing_music.finish_criteria().clone(),
+            },
+            keystrokes: playing_music
+                .keystrokes()
+                .iter()
+                .map(|keystroke| ReplayKeystroke {
+                    time: keystroke.time.as_micros() as u64,
+                    chara: keystroke.chara,
+                })
+                .collect(),
+            duration: playing_music.elapsed_time().as_micros() as u64,
+            finish_reason: playing_music.finish_reason(),
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_micros(self.duration)
+    }
+
+    /// the directory the replays are saved in by default
+    pub fn default_directory() -> Option<PathBuf> {
+        paths::data_directory().map(|directory| directory.join("replays"))
+    }
+
+    /// return true if this replay was recorded on the song of the given PlayingMusic
+    pub fn is_for(&self, playing_music: &PlayingMusic) -> bool {
+        self.song_hash == playing_music.song_hash()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("failed to serialize a replay")
+    }
+
+    pub fn from_json(json: &str) -> io::Result<Self> {
+        serde_json::from_str(json).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        serde_json::to_writer(File::create(path)?, self)
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        serde_json::from_reader(io::BufReader::new(File::open(path)?))
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+/// play a Replay on a PlayingMusic, using a virtual clock
+pub struct ReplayPlayer {
+    replay: Replay,
+    clock: Duration,
+    next_keystroke: usize,
+    speed: f64,
+    paused: bool,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            clock: Duration::default(),
+            next_keystroke: 0,
+            speed: 1.0,
+            paused: false,
+        }
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// restart the PlayingMusic with the settings of the replay, and rewind the replay
+    pub fn prepare(&mut self, playing_music: &mut PlayingMusic) {
+        playing_music.restart();
+        playing_music.set_precision(self.replay.settings.precision);
+        playing_music.set_finish_criteria(self.replay.settings.finish_criteria.clone());
+        self.clock = Duration::default();
+        self.next_keystroke = 0;
+    }
+
+    /// advance the clock by delta (multiplied by the speed), unless paused
+    pub fn advance(&mut self, playing_music: &mut PlayingMusic, delta: Duration) {
+        if self.paused {
+            return;
+        };
+        let target = self.clock + delta.mul_f64(self.speed);
+        self.seek(playing_music, target);
+    }
+
+    /// move the clock to the given time. Seeking backward restart the PlayingMusic.
+    pub fn seek(&mut self, playing_music: &mut PlayingMusic, time: Duration) {
+        let time = time.min(self.replay.duration());
+        if time < self.clock {
+            self.prepare(playing_music);
+        };
+        while let Some(keystroke) = self.replay.keystrokes.get(self.next_keystroke) {
+            if keystroke.duration() > time {
+                break;
+            };
+            playing_music.set_elapsed_time(keystroke.duration());
+            playing_music.add_typed_char(keystroke.chara);
+            self.next_keystroke += 1;
+        }
+        playing_music.set_elapsed_time(time);
+        if time >= self.replay.duration()
+            && self.replay.finish_reason == Some(FinishReason::Explicit)
+        {
+            playing_music.finish();
+        };
+        self.clock = time;
+    }
+
+    pub fn clock(&self) -> Duration {
+        self.clock
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.clock >= self.replay.duration()
+    }
+
+    pub fn play(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: f64) {
+        self.speed = speed.max(0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{PlayingMusic, Replay, ReplayPlayer};
+    use std::time::Duration;
+
+    static SONG: &str = "[Verse]\nHello world, this is a song\n[Chorus]\nla la la";
+
+    fn record() -> PlayingMusic {
+        let mut playing_music = PlayingMusic::new(SONG.to_string());
+        for (count, chara) in "helo\u{8}lo wolrd\u{8}\u{8}\u{8}rld, thus is ssong la"
+            .chars()
+            .enumerate()
+        {
+            playing_music.set_elapsed_time(Duration::from_millis(count as u64 * 150 + 7));
+            playing_music.add_typed_char(chara);
+        }
+        playing_music.set_elapsed_time(Duration::from_secs(10));
+        playing_music.finish();
+        playing_music
+    }
+
+    #[test]
+    fn test_replay_identical() {
+        let original = record();
+        let replay =
+            Replay::from_json(&Replay::from_playing_music(&original, "song".into()).to_json())
+                .unwrap();
+
+        let mut replayed = PlayingMusic::new(SONG.to_string());
+        assert!(replay.is_for(&replayed));
+        let mut player = ReplayPlayer::new(replay);
+        player.prepare(&mut replayed);
+        player.set_speed(2.0);
+        while !player.is_over() {
+            player.advance(&mut replayed, Duration::from_millis(16));
+        }
+        assert_eq!(replayed.get_typed_text(), original.get_typed_text());
+        assert_eq!(replayed.correctness(), original.correctness());
+        assert_eq!(
+            replayed.position_in_source_text(),
+            original.position_in_source_text()
+        );
+        assert_eq!(replayed.result(), original.result());
+
+        // seek back, then forward again
+        player.seek(&mut replayed, Duration::from_millis(1000));
+        assert_eq!(replayed.keystrokes().len(), 7);
+        player.seek(&mut replayed, Duration::from_secs(20));
+        assert_eq!(replayed.correctness(), original.correctness());
+        assert_eq!(replayed.result(), original.result());
+    }
+}
@@ -0,0 +1,415 @@
+use crate::{paths, Replay, ReplaySettings, SessionResult};
+use fs2::FileExt;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cmp::Reverse;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// the version of the database schema written by this version of the game
+const SCHEMA_VERSION: u64 = 1;
+
+/// a finished run of a song
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ScoreRecord {
+    /// the time the run was finished at, in seconds since the unix epoch
+    pub date: u64,
+    pub settings: ReplaySettings,
+    pub result: SessionResult,
+    /// the name of the file of the replay of the run, in the replay directory of the database
+    /// (see ScoreDatabase::replay)
+    pub replay: Option<String>,
+}
+
+impl ScoreRecord {
+    /// create a record of a run finished now
+    pub fn new(settings: ReplaySettings, result: SessionResult) -> Self {
+        Self {
+            date: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or(0),
+            settings,
+            result,
+            replay: None,
+        }
+    }
+
+    /// return true if this run is better than the other one: it has a better correctness, or the
+    /// same correctness in less time
+    pub fn is_better_than(&self, other: &ScoreRecord) -> bool {
+        if self.result.correctness != other.result.correctness {
+            self.result.correctness > other.result.correctness
+        } else {
+            self.result.duration < other.result.duration
+        }
+    }
+}
+
+/// a line of the database file. Songs are identified by their hash, written in hexadecimal.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+struct StoredRecord {
+    version: u64,
+    song_hash: String,
+    song_id: String,
+    #[serde(flatten)]
+    record: ScoreRecord,
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+/// Read a line of the database, upgrading it to the current schema if it was written by an
+/// older version of the game. The lines written before the records had a version are the
+/// version 0: their song hash can be a number instead of an hexadecimal string, and they can
+/// have no song id.
+fn migrate(mut line: Value) -> io::Result<StoredRecord> {
+    let object = line
+        .as_object_mut()
+        .ok_or_else(|| invalid_data("a record isn't a JSON object"))?;
+    let version = match object.get("version") {
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| invalid_data("the version of a record isn't a number"))?,
+        None => 0,
+    };
+    if version > SCHEMA_VERSION {
+        return Err(invalid_data(format!(
+            "the record has the version {}, but only version up to {} are supported",
+            version, SCHEMA_VERSION
+        )));
+    };
+    if version == 0 {
+        if let Some(song_hash) = object.get("song_hash").and_then(Value::as_u64) {
+            object.insert("song_hash".into(), format!("{:016x}", song_hash).into());
+        };
+        object
+            .entry("song_id")
+            .or_insert_with(|| String::new().into());
+        object.insert("version".into(), 1.into());
+    };
+    serde_json::from_value(line).map_err(invalid_data)
+}
+
+/// A database of the finished runs, stored as a file with a JSON record per line, and a file per
+/// replay in the replays directory next to it. Records are only ever appended to the file, while
+/// holding an exclusive lock on it, so multiple instances of the game can share it. The lock is
+/// an advisory lock of the OS, which is released if an instance crash. The records written by
+/// older versions of the game are upgraded when they are read, and the ones written by newer
+/// versions are skipped.
+#[derive(Debug, Clone)]
+pub struct ScoreDatabase {
+    path: PathBuf,
+}
+
+impl ScoreDatabase {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// the database in the XDG data directory
+    pub fn open_default() -> Option<Self> {
+        paths::data_directory().map(|directory| Self::new(directory.join("scores.jsonl")))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn replay_directory(&self) -> PathBuf {
+        self.path.with_file_name("replays")
+    }
+
+    /// write the replay in a new file of the replay directory, and return the name of the file
+    fn write_replay(&self, song_hash: u64, date: u64, replay: &Replay) -> io::Result<String> {
+        let directory = self.replay_directory();
+        fs::create_dir_all(&directory)?;
+        for count in 0.. {
+            let name = format!("{:016x}-{}-{}.json", song_hash, date, count);
+            match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(directory.join(&name))
+            {
+                Ok(file) => {
+                    let mut writer = BufWriter::new(file);
+                    writer.write_all(replay.to_json().as_bytes())?;
+                    writer.flush()?;
+                    return Ok(name);
+                }
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(err),
+            };
+        }
+        unreachable!()
+    }
+
+    /// add a record of the song with the given hash, and save its replay if there is one
+    pub fn add_record(
+        &self,
+        song_hash: u64,
+        song_id: &str,
+        mut record: ScoreRecord,
+        replay: Option<&Replay>,
+    ) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        };
+        if let Some(replay) = replay {
+            record.replay = Some(self.write_replay(song_hash, record.date, replay)?);
+        };
+        let stored = StoredRecord {
+            version: SCHEMA_VERSION,
+            song_hash: format!("{:016x}", song_hash),
+            song_id: song_id.to_string(),
+            record,
+        };
+        let mut line = serde_json::to_vec(&stored).map_err(invalid_data)?;
+        line.push(b'\n');
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&self.path)?;
+        FileExt::lock_exclusive(&file)?;
+        // start a new line if the last one was cut by an instance stopped while writing it
+        if file.seek(SeekFrom::End(0))? > 0 {
+            let mut last_byte = [0];
+            file.seek(SeekFrom::End(-1))?;
+            file.read_exact(&mut last_byte)?;
+            if last_byte[0] != b'\n' {
+                line.insert(0, b'\n');
+            };
+        };
+        // the line is written at once, so the readers never see a part of it
+        file.write_all(&line)
+    }
+
+    /// every record of the given song, in the order they were added
+    pub fn records(&self, song_hash: u64) -> io::Result<Vec<ScoreRecord>> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+        FileExt::lock_shared(&file)?;
+        let song_hash = format!("{:016x}", song_hash);
+        let mut records = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            };
+            let stored: Value = match serde_json::from_str(&line) {
+                Ok(stored) => stored,
+                Err(err) => {
+                    // a line can be cut if an instance was stopped while writing it
+                    warn!("ignored an invalid line of {:?}: {}", self.path, err);
+                    continue;
+                }
+            };
+            let stored = match migrate(stored) {
+                Ok(stored) => stored,
+                Err(err) => {
+                    warn!("ignored a record of {:?}: {}", self.path, err);
+                    continue;
+                }
+            };
+            if stored.song_hash == song_hash {
+                records.push(stored.record);
+            };
+        }
+        Ok(records)
+    }
+
+    /// the replay of a record, if it has one
+    pub fn replay(&self, record: &ScoreRecord) -> io::Result<Option<Replay>> {
+        match &record.replay {
+            Some(name) => Replay::load(&self.replay_directory().join(name)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// the best record of the given song (see ScoreRecord::is_better_than)
+    pub fn personal_best(&self, song_hash: u64) -> io::Result<Option<ScoreRecord>> {
+        let mut best: Option<ScoreRecord> = None;
+        for record in self.records(song_hash)? {
+            if best
+                .as_ref()
+                .map(|best| record.is_better_than(best))
+                .unwrap_or(true)
+            {
+                best = Some(record);
+            };
+        }
+        Ok(best)
+    }
+
+    /// the last count records of the given song, the most recent first
+    pub fn recent_runs(&self, song_hash: u64, count: usize) -> io::Result<Vec<ScoreRecord>> {
+        let mut records = self.records(song_hash)?;
+        records.sort_by_key(|record| Reverse(record.date));
+        records.truncate(count);
+        Ok(records)
+    }
+
+    /// the correctness of every run of the given song, as (date, correctness), the oldest first
+    pub fn progress(&self, song_hash: u64) -> io::Result<Vec<(u64, f64)>> {
+        let mut progress: Vec<(u64, f64)> = self
+            .records(song_hash)?
+            .iter()
+            .map(|record| (record.date, record.result.correctness))
+            .collect();
+        progress.sort_by_key(|(date, _)| *date);
+        Ok(progress)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{PlayingMusic, Replay, ScoreDatabase, ScoreRecord};
+    use std::fs::OpenOptions;
+    use std::io::Write;
+    use std::path::PathBuf;
+    use std::process::{self, Command, Stdio};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::{env, fs};
+
+    fn temporary_database_path() -> PathBuf {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let directory = env::temp_dir().join(format!(
+            "music_typer_test_{}_{}",
+            process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = fs::remove_dir_all(&directory);
+        directory.join("scores.jsonl")
+    }
+
+    fn record(typed: &str, date: u64) -> (ScoreRecord, Replay) {
+        let mut playing_music = PlayingMusic::new("hello world".into());
+        playing_music.add_typed_text(typed);
+        playing_music.finish();
+        let replay = Replay::from_playing_music(&playing_music, "hello".into());
+        let mut record = ScoreRecord::new(replay.settings.clone(), playing_music.result().unwrap());
+        record.date = date;
+        (record, replay)
+    }
+
+    fn add_record(database: &ScoreDatabase, song_hash: u64, typed: &str, date: u64) {
+        let (record, replay) = record(typed, date);
+        database
+            .add_record(song_hash, "hello", record, Some(&replay))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_score_database_queries() {
+        let database = ScoreDatabase::new(temporary_database_path());
+        assert_eq!(database.personal_best(1).unwrap(), None);
+        add_record(&database, 1, "hello", 10);
+        add_record(&database, 1, "hello world", 20);
+        add_record(&database, 1, "hello wrld", 30);
+        add_record(&database, 2, "hello", 40);
+
+        let best = database.personal_best(1).unwrap().unwrap();
+        assert_eq!(best.date, 20);
+        let replay = database.replay(&best).unwrap().unwrap();
+        assert_eq!(replay, record("hello world", 20).1);
+        let recent: Vec<u64> = database
+            .recent_runs(1, 2)
+            .unwrap()
+            .iter()
+            .map(|record| record.date)
+            .collect();
+        assert_eq!(recent, vec![30, 20]);
+        let progress = database.progress(1).unwrap();
+        assert_eq!(progress.len(), 3);
+        assert_eq!(progress[1], (20, 1.0));
+        assert_eq!(database.records(2).unwrap().len(), 1);
+
+        // a cut line and a record from a newer version of the game are ignored
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(database.path())
+            .unwrap();
+        file.write_all(b"{\"version\": 1, \"song_ha").unwrap();
+        assert_eq!(database.records(1).unwrap().len(), 3);
+        add_record(&database, 1, "hello", 50);
+        assert_eq!(database.records(1).unwrap().len(), 4);
+        file.write_all(b"{\"version\": 2}\n").unwrap();
+        assert_eq!(database.records(1).unwrap().len(), 4);
+        fs::remove_dir_all(database.path().parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_score_database_migration() {
+        let database = ScoreDatabase::new(temporary_database_path());
+        add_record(&database, 5, "hello", 10);
+        // a record written before the records had a version
+        let (record, _) = record("hello world", 20);
+        let mut old_line = serde_json::to_value(&record).unwrap();
+        old_line["song_hash"] = 5.into();
+        old_line["replay"] = serde_json::Value::Null;
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(database.path())
+            .unwrap();
+        writeln!(file, "{}", old_line).unwrap();
+
+        let records = database.records(5).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1], record);
+        assert_eq!(database.personal_best(5).unwrap(), Some(record));
+        fs::remove_dir_all(database.path().parent().unwrap()).unwrap();
+    }
+
+    /// the environment variables given to score_database_writer
+    const WRITER_PATH: &str = "MUSIC_TYPER_TEST_DATABASE";
+    const WRITER_INSTANCE: &str = "MUSIC_TYPER_TEST_INSTANCE";
+
+    /// add records to the database given by the environment, in the process started by
+    /// test_score_database_concurrent_writes
+    #[test]
+    #[ignore]
+    fn score_database_writer() {
+        let path = match env::var_os(WRITER_PATH) {
+            Some(path) => PathBuf::from(path),
+            None => return,
+        };
+        let instance: u64 = env::var(WRITER_INSTANCE).unwrap().parse().unwrap();
+        let database = ScoreDatabase::new(path);
+        for count in 0..20 {
+            add_record(&database, 7, "hello", instance * 100 + count);
+        }
+    }
+
+    #[test]
+    fn test_score_database_concurrent_writes() {
+        let path = temporary_database_path();
+        // every instance of the game is a process running score_database_writer
+        let instances: Vec<_> = (0..2)
+            .map(|instance| {
+                Command::new(env::current_exe().unwrap())
+                    .arg("--ignored")
+                    .arg("--exact")
+                    .arg("score_database::tests::score_database_writer")
+                    .env(WRITER_PATH, &path)
+                    .env(WRITER_INSTANCE, instance.to_string())
+                    .stdout(Stdio::null())
+                    .spawn()
+                    .unwrap()
+            })
+            .collect();
+        for mut instance in instances {
+            assert!(instance.wait().unwrap().success());
+        }
+        let database = ScoreDatabase::new(path.clone());
+        assert_eq!(database.records(7).unwrap().len(), 40);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
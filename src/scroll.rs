@@ -0,0 +1,181 @@
+/// how a scrolled value moves to its target
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum ScrollEasing {
+    /// jump to the target immediately
+    Instant,
+    /// a critically damped spring, that follow the target as fast as possible without
+    /// overshooting. A bigger angular frequency make it faster.
+    Spring { angular_frequency: f32 },
+    /// move at a constant speed, reaching the target after the duration in seconds
+    Linear { duration: f32 },
+    /// start fast then slow down, reaching the target after the duration in seconds
+    EaseOutCubic { duration: f32 },
+}
+
+impl Default for ScrollEasing {
+    fn default() -> Self {
+        ScrollEasing::Spring {
+            angular_frequency: 12.0,
+        }
+    }
+}
+
+/// a value, like the vertical position of the lyrics, that move smoothly to its target. It only
+/// depends on the elapsed time, not on how often it is updated.
+#[derive(Debug, Clone)]
+pub struct ScrollAnimation {
+    easing: ScrollEasing,
+    position: f32,
+    /// only used by the spring
+    velocity: f32,
+    /// the position when the target last changed, only used by the curves
+    start: f32,
+    target: f32,
+    /// the time elapsed since the target last changed, in seconds
+    elapsed: f32,
+}
+
+impl ScrollAnimation {
+    pub fn new(easing: ScrollEasing, position: f32) -> Self {
+        Self {
+            easing,
+            position,
+            velocity: 0.0,
+            start: position,
+            target: position,
+            elapsed: 0.0,
+        }
+    }
+
+    pub fn position(&self) -> f32 {
+        self.position
+    }
+
+    pub fn target(&self) -> f32 {
+        self.target
+    }
+
+    pub fn easing(&self) -> ScrollEasing {
+        self.easing
+    }
+
+    pub fn is_moving(&self) -> bool {
+        self.position != self.target
+    }
+
+    pub fn set_easing(&mut self, easing: ScrollEasing) {
+        self.easing = easing;
+        self.start = self.position;
+        self.elapsed = 0.0;
+    }
+
+    /// start moving to the given target, from the actual position
+    pub fn set_target(&mut self, target: f32) {
+        if target == self.target {
+            return;
+        };
+        self.target = target;
+        self.start = self.position;
+        self.elapsed = 0.0;
+    }
+
+    /// place the value at the given position, without animation
+    pub fn snap(&mut self, position: f32) {
+        *self = Self::new(self.easing, position);
+    }
+
+    /// move the value by the given amount of time, in seconds
+    pub fn update(&mut self, delta_seconds: f32) {
+        if !self.is_moving() {
+            return;
+        };
+        self.elapsed += delta_seconds;
+        match self.easing {
+            ScrollEasing::Instant => self.position = self.target,
+            ScrollEasing::Spring { angular_frequency } => {
+                // exact solution of the critically damped spring, so that the movement doesn't
+                // depend on the frame rate
+                let offset = self.position - self.target;
+                let temp = (self.velocity + angular_frequency * offset) * delta_seconds;
+                let decay = (-angular_frequency * delta_seconds).exp();
+                self.velocity = (self.velocity - angular_frequency * temp) * decay;
+                self.position = self.target + (offset + temp) * decay;
+                if (self.position - self.target).abs() < 0.001 && self.velocity.abs() < 0.01 {
+                    self.position = self.target;
+                    self.velocity = 0.0;
+                };
+            }
+            ScrollEasing::Linear { duration } => {
+                self.ease(duration, |progress| progress);
+            }
+            ScrollEasing::EaseOutCubic { duration } => {
+                self.ease(duration, |progress| 1.0 - (1.0 - progress).powi(3));
+            }
+        };
+    }
+
+    /// move the value along the given curve, that map the progress from 0 to 1 to the covered
+    /// part of the distance
+    fn ease(&mut self, duration: f32, curve: impl Fn(f32) -> f32) {
+        if self.elapsed >= duration {
+            self.position = self.target;
+            return;
+        };
+        let progress = curve(self.elapsed / duration);
+        self.position = self.start + (self.target - self.start) * progress;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ScrollAnimation, ScrollEasing};
+
+    #[test]
+    fn test_scroll_spring() {
+        let easing = ScrollEasing::Spring {
+            angular_frequency: 10.0,
+        };
+        let mut fast_frame_rate = ScrollAnimation::new(easing, 0.0);
+        let mut slow_frame_rate = ScrollAnimation::new(easing, 0.0);
+        fast_frame_rate.set_target(100.0);
+        slow_frame_rate.set_target(100.0);
+        for _ in 0..24 {
+            fast_frame_rate.update(1.0 / 120.0);
+        }
+        for _ in 0..3 {
+            slow_frame_rate.update(1.0 / 15.0);
+        }
+        assert!(fast_frame_rate.position() > 10.0 && fast_frame_rate.position() < 90.0);
+        assert!((fast_frame_rate.position() - slow_frame_rate.position()).abs() < 0.01);
+        for _ in 0..100 {
+            fast_frame_rate.update(1.0 / 30.0);
+            assert!(fast_frame_rate.position() <= 100.0);
+        }
+        assert_eq!(fast_frame_rate.position(), 100.0);
+        assert!(!fast_frame_rate.is_moving());
+    }
+
+    #[test]
+    fn test_scroll_curves() {
+        let mut linear = ScrollAnimation::new(ScrollEasing::Linear { duration: 0.5 }, 0.0);
+        linear.set_target(10.0);
+        linear.update(0.25);
+        assert!((linear.position() - 5.0).abs() < 0.0001);
+        // changing the target start again from the actual position
+        linear.set_target(-5.0);
+        linear.update(0.25);
+        assert!(linear.position().abs() < 0.0001);
+        linear.update(0.3);
+        assert_eq!(linear.position(), -5.0);
+
+        let mut ease_out = ScrollAnimation::new(ScrollEasing::EaseOutCubic { duration: 1.0 }, 0.0);
+        ease_out.set_target(8.0);
+        ease_out.update(0.5);
+        assert!((ease_out.position() - 7.0).abs() < 0.0001);
+
+        let mut instant = ScrollAnimation::new(ScrollEasing::Instant, 0.0);
+        instant.set_target(3.0);
+        instant.update(0.0);
+        assert_eq!(instant.position(), 3.0);
+    }
+}
@@ -0,0 +1,48 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// the reason a song is considered finished
+#[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
+pub enum FinishReason {
+    /// the position tracker reached the end of the text
+    EndOfText,
+    /// the player explicitly asked to finish the song
+    Explicit,
+    /// the time limit was reached
+    TimeLimit,
+}
+
+/// the conditions under which a PlayingMusic is finished. It can always be finished explicitly.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct FinishCriteria {
+    /// finish when the position tracker reach the end of the text
+    pub end_of_text: bool,
+    pub time_limit: Option<Duration>,
+}
+
+impl Default for FinishCriteria {
+    fn default() -> Self {
+        Self {
+            end_of_text: true,
+            time_limit: None,
+        }
+    }
+}
+
+/// the final result of a song
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct SessionResult {
+    pub finish_reason: FinishReason,
+    pub correctness: f64,
+    /// the levenshtein distance between the typed text and the source text
+    pub distance: u32,
+    pub duration: Duration,
+    /// the number of character of the (transformed) source text
+    pub source_char_count: usize,
+    /// the number of character of the (transformed) typed text
+    pub typed_char_count: usize,
+    /// the number of key pressed, including backspaces
+    pub keystroke_count: usize,
+    pub backspace_count: usize,
+    pub position_in_source_text: usize,
+}
@@ -0,0 +1,472 @@
+use crate::{paths, Theme};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// how a song is played
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameMode {
+    #[default]
+    Normal,
+    /// the runs are not saved in the score database
+    Practice,
+    /// the lyrics are hidden until they are typed
+    Blind,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 3] = [GameMode::Normal, GameMode::Practice, GameMode::Blind];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GameMode::Normal => "normal",
+            GameMode::Practice => "practice",
+            GameMode::Blind => "blind",
+        }
+    }
+
+    /// the next mode, to cycle through them
+    pub fn next(self) -> Self {
+        match self {
+            GameMode::Normal => GameMode::Practice,
+            GameMode::Practice => GameMode::Blind,
+            GameMode::Blind => GameMode::Normal,
+        }
+    }
+}
+
+impl fmt::Display for GameMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for GameMode {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|mode| mode.name() == name.to_lowercase())
+            .ok_or_else(|| {
+                let names: Vec<&str> = Self::ALL.iter().map(|mode| mode.name()).collect();
+                format!("unknown mode {:?}, it must be {}", name, names.join(", "))
+            })
+    }
+}
+
+/// how the lyrics are placed. When the layout follow the size of the window, the sizes are the
+/// ones of a window 1080 pixels high, and the numbers of lines are maximums.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplaySettings {
+    pub show_chords: bool,
+    pub distance_between_line: f32,
+    /// the number of typed lines displayed above the current line
+    pub top_displayed_line: usize,
+    /// the number of lines to type displayed below the current line
+    pub bottom_displayed_line: usize,
+    /// the number of lines over which the lines entering or leaving the displayed lines fade
+    pub fade_lines: f32,
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        Self {
+            show_chords: true,
+            distance_between_line: 100.0,
+            top_displayed_line: 3,
+            bottom_displayed_line: 3,
+            fade_lines: 1.0,
+        }
+    }
+}
+
+/// the settings of the game, read from a TOML file. Every field is optional in the file.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub mode: GameMode,
+    pub fullscreen: bool,
+    /// the font of every text, as a path in the assets folder
+    pub font: String,
+    /// the size of the lyrics
+    pub font_size: f32,
+    /// the precision used to compute the position in the lyrics (see PlayingMusic::precision)
+    pub precision: usize,
+    /// the directory the songs are read from, instead of the default one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub library: Option<PathBuf>,
+    /// the name of a preset theme, of a theme of the theme directory or the path of a theme file
+    pub theme: String,
+    /// the fonts the lyrics are written with when the main font has no glyph for a character,
+    /// tried in order, as paths in the assets folder. There is none by default: to display
+    /// japanese lyrics for example, copy a font like NotoSansJP-Bold.otf in assets/fonts and add
+    /// fallback_fonts = ["fonts/NotoSansJP-Bold.otf"]. A font that can't be loaded is skipped.
+    pub fallback_fonts: Vec<String>,
+    pub display: DisplaySettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            mode: GameMode::Normal,
+            fullscreen: false,
+            font: "fonts/FiraSans-Bold.ttf".into(),
+            font_size: 64.0,
+            precision: 5,
+            library: None,
+            theme: "default".into(),
+            fallback_fonts: Vec::new(),
+            display: DisplaySettings::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// the file the settings are read from and written to by default
+    pub fn default_path() -> Option<PathBuf> {
+        paths::config_directory().map(|directory| directory.join("settings.toml"))
+    }
+
+    pub fn from_toml(text: &str) -> io::Result<Self> {
+        let settings: Self =
+            toml::from_str(text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        settings
+            .validate()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        Ok(settings)
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string_pretty(self).expect("failed to serialize the settings")
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::from_toml(&fs::read_to_string(path)?)
+    }
+
+    /// read the settings file, the default settings being used if it doesn't exist
+    pub fn load_or_default(path: &Path) -> io::Result<Self> {
+        match Self::load(path) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            result => result,
+        }
+    }
+
+    /// write the settings, creating the directory of the file if needed
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        };
+        fs::write(path, self.to_toml())
+    }
+
+    /// return a description of the first value that is out of its range
+    pub fn validate(&self) -> Result<(), String> {
+        let display = &self.display;
+        let ranges = [
+            ("font_size", f64::from(self.font_size), 8.0, 256.0),
+            ("precision", self.precision as f64, 1.0, 20.0),
+            (
+                "display.distance_between_line",
+                f64::from(display.distance_between_line),
+                8.0,
+                512.0,
+            ),
+            (
+                "display.top_displayed_line",
+                display.top_displayed_line as f64,
+                0.0,
+                20.0,
+            ),
+            (
+                "display.bottom_displayed_line",
+                display.bottom_displayed_line as f64,
+                0.0,
+                20.0,
+            ),
+            (
+                "display.fade_lines",
+                f64::from(display.fade_lines),
+                0.0,
+                5.0,
+            ),
+        ];
+        for &(name, value, min, max) in ranges.iter() {
+            if !(min..=max).contains(&value) {
+                return Err(format!(
+                    "{} is {}, it must be from {} to {}",
+                    name, value, min, max
+                ));
+            };
+        }
+        if self.font.trim().is_empty() {
+            return Err("font is empty".into());
+        };
+        if self.theme.trim().is_empty() {
+            return Err("theme is empty".into());
+        };
+        for (count, fallback) in self.fallback_fonts.iter().enumerate() {
+            if fallback.trim().is_empty() {
+                return Err(format!("the path of fallback_fonts {} is empty", count + 1));
+            };
+        }
+        Ok(())
+    }
+}
+
+/// values that replace some settings for a single run of the game, like the ones given on the
+/// command line, and that are not saved in the settings file
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct SettingsOverrides {
+    pub mode: Option<GameMode>,
+    pub fullscreen: Option<bool>,
+    pub library: Option<PathBuf>,
+}
+
+impl SettingsOverrides {
+    /// the settings with the overridden values
+    pub fn apply(&self, settings: &Settings) -> Settings {
+        let mut settings = settings.clone();
+        if let Some(mode) = self.mode {
+            settings.mode = mode;
+        };
+        if let Some(fullscreen) = self.fullscreen {
+            settings.fullscreen = fullscreen;
+        };
+        if let Some(library) = &self.library {
+            settings.library = Some(library.clone());
+        };
+        settings
+    }
+
+    /// the settings to save instead of settings: the overridden values are the ones of saved, the
+    /// settings that were last saved
+    pub fn unapply(&self, settings: &Settings, saved: &Settings) -> Settings {
+        let mut settings = settings.clone();
+        if self.mode.is_some() {
+            settings.mode = saved.mode;
+        };
+        if self.fullscreen.is_some() {
+            settings.fullscreen = saved.fullscreen;
+        };
+        if self.library.is_some() {
+            settings.library = saved.library.clone();
+        };
+        settings
+    }
+
+    /// stop overriding the setting of the item, after the player changed it in the menu
+    pub fn forget(&mut self, item: SettingsItem) {
+        match item {
+            SettingsItem::Mode => self.mode = None,
+            SettingsItem::Fullscreen => self.fullscreen = None,
+            _ => (),
+        };
+    }
+}
+
+/// a setting that can be changed in the settings menu of the game
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum SettingsItem {
+    Mode,
+    Fullscreen,
+    ShowChords,
+    FontSize,
+    DistanceBetweenLine,
+    TopDisplayedLine,
+    BottomDisplayedLine,
+    FadeLines,
+    Precision,
+    Theme,
+}
+
+impl SettingsItem {
+    pub const ALL: [SettingsItem; 10] = [
+        SettingsItem::Mode,
+        SettingsItem::Fullscreen,
+        SettingsItem::ShowChords,
+        SettingsItem::FontSize,
+        SettingsItem::DistanceBetweenLine,
+        SettingsItem::TopDisplayedLine,
+        SettingsItem::BottomDisplayedLine,
+        SettingsItem::FadeLines,
+        SettingsItem::Precision,
+        SettingsItem::Theme,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SettingsItem::Mode => "mode",
+            SettingsItem::Fullscreen => "fullscreen",
+            SettingsItem::ShowChords => "show chords",
+            SettingsItem::FontSize => "font size",
+            SettingsItem::DistanceBetweenLine => "distance between lines",
+            SettingsItem::TopDisplayedLine => "lines above",
+            SettingsItem::BottomDisplayedLine => "lines below",
+            SettingsItem::FadeLines => "fading lines",
+            SettingsItem::Precision => "precision",
+            SettingsItem::Theme => "theme",
+        }
+    }
+
+    /// the value of the item in the settings, as displayed in the menu
+    pub fn value_text(self, settings: &Settings) -> String {
+        let on_off = |value| if value { "on" } else { "off" }.to_string();
+        let display = &settings.display;
+        match self {
+            SettingsItem::Mode => settings.mode.to_string(),
+            SettingsItem::Fullscreen => on_off(settings.fullscreen),
+            SettingsItem::ShowChords => on_off(display.show_chords),
+            SettingsItem::FontSize => format!("{:.0}", settings.font_size),
+            SettingsItem::DistanceBetweenLine => format!("{:.0}", display.distance_between_line),
+            SettingsItem::TopDisplayedLine => display.top_displayed_line.to_string(),
+            SettingsItem::BottomDisplayedLine => display.bottom_displayed_line.to_string(),
+            SettingsItem::FadeLines => format!("{:.1}", display.fade_lines),
+            SettingsItem::Precision => settings.precision.to_string(),
+            SettingsItem::Theme => settings.theme.clone(),
+        }
+    }
+
+    /// change the value of the item one step up, or down if up is false. The values stay in the
+    /// ranges accepted by Settings::validate, and the booleans, the mode and the preset themes are
+    /// cycled.
+    pub fn adjust(self, settings: &mut Settings, up: bool) {
+        let step = |value: f32, step: f32, min: f32, max: f32| {
+            let value = if up { value + step } else { value - step };
+            value.max(min).min(max)
+        };
+        let count_step = |value: usize, max: usize| {
+            if up {
+                (value + 1).min(max)
+            } else {
+                value.saturating_sub(1)
+            }
+        };
+        let display = &mut settings.display;
+        match self {
+            SettingsItem::Mode => {
+                settings.mode = if up {
+                    settings.mode.next()
+                } else {
+                    settings.mode.next().next()
+                }
+            }
+            SettingsItem::Fullscreen => settings.fullscreen = !settings.fullscreen,
+            SettingsItem::ShowChords => display.show_chords = !display.show_chords,
+            SettingsItem::FontSize => {
+                settings.font_size = step(settings.font_size, 4.0, 8.0, 256.0)
+            }
+            SettingsItem::DistanceBetweenLine => {
+                display.distance_between_line = step(display.distance_between_line, 5.0, 8.0, 512.0)
+            }
+            SettingsItem::TopDisplayedLine => {
+                display.top_displayed_line = count_step(display.top_displayed_line, 20)
+            }
+            SettingsItem::BottomDisplayedLine => {
+                display.bottom_displayed_line = count_step(display.bottom_displayed_line, 20)
+            }
+            SettingsItem::FadeLines => display.fade_lines = step(display.fade_lines, 0.5, 0.0, 5.0),
+            SettingsItem::Precision => {
+                settings.precision = count_step(settings.precision, 20).max(1)
+            }
+            SettingsItem::Theme => {
+                // a theme that is not a preset is left for the first or the last preset
+                let presets: Vec<&str> = Theme::preset_names().collect();
+                let index = match presets.iter().position(|name| *name == settings.theme) {
+                    Some(index) if up => (index + 1) % presets.len(),
+                    Some(index) => (index + presets.len() - 1) % presets.len(),
+                    None if up => 0,
+                    None => presets.len() - 1,
+                };
+                settings.theme = presets[index].to_string();
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{GameMode, Settings, SettingsItem, SettingsOverrides};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_settings() {
+        let settings = Settings::from_toml(
+            "mode = \"blind\"\nlibrary = \"songs\"\n[display]\ntop_displayed_line = 5\n",
+        )
+        .unwrap();
+        assert_eq!(settings.mode, GameMode::Blind);
+        assert_eq!(settings.library, Some(PathBuf::from("songs")));
+        assert_eq!(settings.font, Settings::default().font);
+        assert_eq!(settings.display.top_displayed_line, 5);
+        assert_eq!(settings.display.bottom_displayed_line, 3);
+        assert_eq!(settings.theme, "default");
+        assert_eq!(settings.fallback_fonts, Settings::default().fallback_fonts);
+        let written = settings.to_toml();
+        assert_eq!(Settings::from_toml(&written).unwrap().to_toml(), written);
+
+        assert!(Settings::from_toml("mode = \"hard\"").is_err());
+        assert!(Settings::from_toml("font_size = 1000.0").is_err());
+        assert!(Settings::from_toml("theme = \"\"").is_err());
+
+        let settings = Settings::from_toml("fallback_fonts = [\"fonts/cjk.otf\"]").unwrap();
+        assert_eq!(settings.fallback_fonts, vec!["fonts/cjk.otf".to_string()]);
+        assert_eq!(Settings::from_toml(&settings.to_toml()).unwrap(), settings);
+        assert!(Settings::from_toml("fallback_fonts = [\"\"]").is_err());
+
+        assert_eq!("Practice".parse(), Ok(GameMode::Practice));
+        assert!("hard".parse::<GameMode>().is_err());
+    }
+
+    #[test]
+    fn test_settings_items() {
+        let mut settings = Settings::default();
+        SettingsItem::FontSize.adjust(&mut settings, true);
+        assert_eq!(SettingsItem::FontSize.value_text(&settings), "68");
+        SettingsItem::Mode.adjust(&mut settings, false);
+        assert_eq!(settings.mode, GameMode::Blind);
+        for _ in 0..30 {
+            SettingsItem::Precision.adjust(&mut settings, false);
+            SettingsItem::TopDisplayedLine.adjust(&mut settings, true);
+            SettingsItem::FadeLines.adjust(&mut settings, false);
+        }
+        assert_eq!(settings.precision, 1);
+        assert_eq!(settings.display.top_displayed_line, 20);
+        assert_eq!(settings.display.fade_lines, 0.0);
+        SettingsItem::Theme.adjust(&mut settings, true);
+        assert_eq!(settings.theme, "high_contrast");
+        SettingsItem::Theme.adjust(&mut settings, false);
+        SettingsItem::Theme.adjust(&mut settings, false);
+        assert_eq!(settings.theme, "protanopia");
+        assert_eq!(settings.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_settings_overrides() {
+        let saved = Settings::default();
+        let mut overrides = SettingsOverrides {
+            mode: Some(GameMode::Practice),
+            library: Some(PathBuf::from("songs")),
+            ..Default::default()
+        };
+        let mut settings = overrides.apply(&saved);
+        assert_eq!(settings.mode, GameMode::Practice);
+        assert_eq!(settings.library, Some(PathBuf::from("songs")));
+        SettingsItem::FontSize.adjust(&mut settings, true);
+        let written = overrides.unapply(&settings, &saved);
+        assert_eq!(written.mode, GameMode::Normal);
+        assert_eq!(written.library, None);
+        assert_eq!(written.font_size, 68.0);
+        // a setting changed by the player is saved
+        SettingsItem::Mode.adjust(&mut settings, true);
+        overrides.forget(SettingsItem::Mode);
+        assert_eq!(overrides.unapply(&settings, &written).mode, GameMode::Blind);
+    }
+}
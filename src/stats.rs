@@ -0,0 +1,194 @@
+use crate::{AlignmentStatus, PlayingMusic};
+use std::time::Duration;
+
+/// the number of character considered to be a word, for the words per minute
+const CHARS_PER_WORD: f64 = 5.0;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum KeystrokeKind {
+    /// a character that was added to the typed text
+    Character,
+    /// a space or an ignored character, that only separate words
+    Separator,
+    Backspace,
+}
+
+/// a key pressed by the player. time is the elapsed time of the song when it was pressed.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Keystroke {
+    pub time: Duration,
+    pub chara: char,
+    pub kind: KeystrokeKind,
+}
+
+/// statistics about the typing speed and accuracy of a PlayingMusic
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct TypingStats {
+    pub elapsed_time: Duration,
+    /// the number of keystroke that aren't backspace
+    pub typed_keystroke_count: usize,
+    pub backspace_count: usize,
+    /// the number of typed character that are the same as the source text
+    pub correct_char_count: usize,
+    /// the number of substituted, missing or extra character that are still in the typed text
+    pub uncorrected_error_count: usize,
+    /// words per minute, counting every keystroke that isn't a backspace
+    pub gross_wpm: f64,
+    /// the gross wpm, minus the uncorrected errors per minute
+    pub net_wpm: f64,
+    /// characters of the typed text per minute
+    pub cpm: f64,
+    /// the correct characters, divided by the keystrokes that aren't backspaces. Mistakes that
+    /// were corrected are still counted.
+    pub raw_accuracy: f64,
+    /// the correct characters, divided by the correct characters and the uncorrected errors
+    pub corrected_accuracy: f64,
+    /// the backspaces, divided by every keystroke
+    pub backspace_ratio: f64,
+}
+
+/// divide, returning 0 when dividing by 0
+fn ratio(numerator: f64, denominator: f64) -> f64 {
+    if denominator > 0.0 {
+        numerator / denominator
+    } else {
+        0.0
+    }
+}
+
+impl TypingStats {
+    /// compute the statistics of a PlayingMusic. While it is played, the correct characters and
+    /// the errors are the live counts of the PlayingMusic, and the typed text is only aligned
+    /// with the whole source text once it is finished.
+    pub fn compute(playing_music: &PlayingMusic) -> Self {
+        let keystrokes = playing_music.keystrokes();
+        let elapsed_time = playing_music.elapsed_time();
+        let minutes = elapsed_time.as_secs_f64() / 60.0;
+        let backspace_count = keystrokes
+            .iter()
+            .filter(|keystroke| keystroke.kind == KeystrokeKind::Backspace)
+            .count();
+        let typed_keystroke_count = keystrokes.len() - backspace_count;
+
+        let (correct_char_count, uncorrected_error_count) = if playing_music.is_finished() {
+            let mut correct_char_count = 0;
+            let mut uncorrected_error_count = 0;
+            for aligned in playing_music.alignment(false) {
+                match aligned.status {
+                    AlignmentStatus::Correct => correct_char_count += 1,
+                    AlignmentStatus::Untyped => (),
+                    _ => uncorrected_error_count += 1,
+                }
+            }
+            (correct_char_count, uncorrected_error_count)
+        } else {
+            playing_music.live_counts()
+        };
+
+        let gross_wpm = ratio(typed_keystroke_count as f64 / CHARS_PER_WORD, minutes);
+        let net_wpm = (gross_wpm - ratio(uncorrected_error_count as f64, minutes)).max(0.0);
+        Self {
+            elapsed_time,
+            typed_keystroke_count,
+            backspace_count,
+            correct_char_count,
+            uncorrected_error_count,
+            gross_wpm,
+            net_wpm,
+            cpm: ratio(
+                playing_music.get_typed_text().chars().count() as f64,
+                minutes,
+            ),
+            raw_accuracy: ratio(correct_char_count as f64, typed_keystroke_count as f64),
+            corrected_accuracy: ratio(
+                correct_char_count as f64,
+                (correct_char_count + uncorrected_error_count) as f64,
+            ),
+            backspace_ratio: ratio(backspace_count as f64, keystrokes.len() as f64),
+        }
+    }
+}
+
+/// compute the gross words per minute over time. Every step, the speed of the keystrokes typed
+/// in the previous window of time is returned, as (time, wpm).
+pub fn rolling_speed(
+    keystrokes: &[Keystroke],
+    end: Duration,
+    window: Duration,
+    step: Duration,
+) -> Vec<(Duration, f64)> {
+    let mut result = Vec::new();
+    if step == Duration::default() || window == Duration::default() {
+        return result;
+    };
+    let window_minutes = window.as_secs_f64() / 60.0;
+    let mut time = step;
+    while time <= end {
+        let typed_in_window = keystrokes
+            .iter()
+            .filter(|keystroke| {
+                keystroke.kind != KeystrokeKind::Backspace
+                    && keystroke.time <= time
+                    && keystroke.time + window > time
+            })
+            .count();
+        result.push((
+            time,
+            typed_in_window as f64 / CHARS_PER_WORD / window_minutes,
+        ));
+        time += step;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{rolling_speed, KeystrokeKind, PlayingMusic};
+    use std::time::Duration;
+
+    #[test]
+    fn test_typing_stats() {
+        let mut playing_music = PlayingMusic::new("hello world".to_string());
+        for (count, chara) in "helli\u{8}o, wrld".chars().enumerate() {
+            playing_music.set_elapsed_time(Duration::from_secs(count as u64 * 2));
+            playing_music.add_typed_char(chara);
+        }
+        playing_music.set_elapsed_time(Duration::from_secs(30));
+        assert_eq!(playing_music.keystrokes().len(), 13);
+        assert_eq!(playing_music.keystrokes()[5].kind, KeystrokeKind::Backspace);
+        assert_eq!(playing_music.keystrokes()[7].kind, KeystrokeKind::Separator);
+        assert_eq!(playing_music.keystrokes()[7].time, Duration::from_secs(14));
+
+        let stats = playing_music.stats();
+        assert_eq!(stats.typed_keystroke_count, 12);
+        assert_eq!(stats.backspace_count, 1);
+        assert_eq!(stats.correct_char_count, 10);
+        assert_eq!(stats.uncorrected_error_count, 1);
+        assert!((stats.gross_wpm - 4.8).abs() < 0.0000001);
+        assert!((stats.net_wpm - 2.8).abs() < 0.0000001);
+        assert!((stats.cpm - 20.0).abs() < 0.0000001);
+        assert!((stats.raw_accuracy - 10.0 / 12.0).abs() < 0.0000001);
+        assert!((stats.corrected_accuracy - 10.0 / 11.0).abs() < 0.0000001);
+        assert!((stats.backspace_ratio - 1.0 / 13.0).abs() < 0.0000001);
+        // the full alignment, once the song is finished, gives the same counts
+        playing_music.finish();
+        let finished_stats = playing_music.stats();
+        assert_eq!(finished_stats.correct_char_count, 10);
+        assert_eq!(finished_stats.uncorrected_error_count, 1);
+
+        let speed = rolling_speed(
+            playing_music.keystrokes(),
+            Duration::from_secs(30),
+            Duration::from_secs(12),
+            Duration::from_secs(10),
+        );
+        assert_eq!(speed.len(), 3);
+        assert_eq!(speed[0].0, Duration::from_secs(10));
+        // the keystrokes at 0, 2, 4, 6 and 8 seconds, the one at 10 being a backspace
+        assert!((speed[0].1 - 5.0).abs() < 0.0000001);
+        // the keystrokes at 12, 14, 16, 18 and 20 seconds
+        assert!((speed[1].1 - 5.0).abs() < 0.0000001);
+        // the keystrokes at 20, 22 and 24 seconds
+        assert!((speed[2].1 - 3.0).abs() < 0.0000001);
+    }
+}
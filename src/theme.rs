@@ -0,0 +1,322 @@
+use crate::paths;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// the themes shipped with the game, by name
+static PRESETS: [(&str, &str); 4] = [
+    ("default", include_str!("../assets/themes/default.toml")),
+    (
+        "high_contrast",
+        include_str!("../assets/themes/high_contrast.toml"),
+    ),
+    (
+        "deuteranopia",
+        include_str!("../assets/themes/deuteranopia.toml"),
+    ),
+    (
+        "protanopia",
+        include_str!("../assets/themes/protanopia.toml"),
+    ),
+];
+
+/// a color, written as "#rrggbb" or "#rrggbbaa" in the theme files. The components are from
+/// 0 to 1, in the order red, green, blue and alpha.
+#[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct RgbaColor(pub [f32; 4]);
+
+impl RgbaColor {
+    pub const fn rgb(red: f32, green: f32, blue: f32) -> Self {
+        Self([red, green, blue, 1.0])
+    }
+
+    pub const fn rgba(red: f32, green: f32, blue: f32, alpha: f32) -> Self {
+        Self([red, green, blue, alpha])
+    }
+}
+
+impl FromStr for RgbaColor {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid color {:?}, it must be #rrggbb or #rrggbbaa", text);
+        let hex = text.strip_prefix('#').ok_or_else(invalid)?;
+        if !(hex.len() == 6 || hex.len() == 8)
+            || !hex.chars().all(|chara| chara.is_ascii_hexdigit())
+        {
+            return Err(invalid());
+        };
+        let mut components = [1.0; 4];
+        for (count, component) in components.iter_mut().enumerate().take(hex.len() / 2) {
+            let byte =
+                u8::from_str_radix(&hex[count * 2..count * 2 + 2], 16).map_err(|_| invalid())?;
+            *component = f32::from(byte) / 255.0;
+        }
+        Ok(Self(components))
+    }
+}
+
+impl TryFrom<String> for RgbaColor {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        text.parse()
+    }
+}
+
+impl From<RgbaColor> for String {
+    fn from(color: RgbaColor) -> Self {
+        let byte = |component: f32| (component.clamp(0.0, 1.0) * 255.0).round() as u8;
+        let [red, green, blue, alpha] = color.0;
+        let mut text = format!("#{:02x}{:02x}{:02x}", byte(red), byte(green), byte(blue));
+        if byte(alpha) != 255 {
+            text.push_str(&format!("{:02x}", byte(alpha)));
+        };
+        text
+    }
+}
+
+/// the colors of the typing view
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TypingColors {
+    /// the untyped characters of the current line
+    pub current: RgbaColor,
+    /// the typed text in the input field
+    pub typed: RgbaColor,
+    /// the marker of a space that was typed but is not in the typed text yet
+    pub pending_space: RgbaColor,
+    /// the characters, depending on their alignment status
+    pub correct: RgbaColor,
+    pub substituted: RgbaColor,
+    pub missing: RgbaColor,
+    pub extra: RgbaColor,
+    pub untyped: RgbaColor,
+    /// the characters that aren't typed, like section headings
+    pub not_typed: RgbaColor,
+    pub caret: RgbaColor,
+    /// the lines that aren't typed yet, and the header and the statistics
+    pub non_current: RgbaColor,
+    pub chords: RgbaColor,
+    pub section_heading: RgbaColor,
+    pub ghost: RgbaColor,
+}
+
+impl Default for TypingColors {
+    fn default() -> Self {
+        let red = RgbaColor::rgb(1.0, 0.0, 0.0);
+        let yellow = RgbaColor::rgb(1.0, 1.0, 0.0);
+        let white = RgbaColor::rgb(1.0, 1.0, 1.0);
+        let gray = RgbaColor::rgb(0.5, 0.5, 0.5);
+        Self {
+            current: red,
+            typed: yellow,
+            pending_space: RgbaColor::rgba(1.0, 1.0, 0.0, 0.4),
+            correct: RgbaColor::rgb(0.4, 0.9, 0.4),
+            substituted: RgbaColor::rgb(1.0, 0.6, 0.0),
+            missing: red,
+            extra: RgbaColor::rgb(0.8, 0.4, 1.0),
+            untyped: white,
+            not_typed: gray,
+            caret: yellow,
+            non_current: white,
+            chords: gray,
+            section_heading: gray,
+            ghost: RgbaColor::rgba(0.5, 0.5, 1.0, 0.8),
+        }
+    }
+}
+
+/// how the highlighted items of the menus are told apart from the others
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmphasisStyle {
+    /// only by their color
+    Color,
+    /// by their color and a marker before them, for the players that can't tell the colors apart
+    Marker,
+}
+
+/// the colors and the font of the game, read from a TOML file. Every field is optional in the
+/// file.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    /// the preset name or the file the theme was read from
+    #[serde(skip)]
+    pub name: String,
+    /// the font of every text, as a path in the assets folder, instead of the one of the settings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font: Option<String>,
+    pub emphasis_style: EmphasisStyle,
+    pub background: RgbaColor,
+    pub text: RgbaColor,
+    /// the help and the secondary texts
+    pub dim_text: RgbaColor,
+    /// the highlighted items of the menus
+    pub highlight: RgbaColor,
+    /// the texts that must catch the eye, like the pause or the search query
+    pub emphasis: RgbaColor,
+    pub button: RgbaColor,
+    pub button_hovered: RgbaColor,
+    pub typing: TypingColors,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            name: "default".into(),
+            font: None,
+            emphasis_style: EmphasisStyle::Color,
+            background: RgbaColor::rgb(0.4, 0.4, 0.4),
+            text: RgbaColor::rgb(1.0, 1.0, 1.0),
+            dim_text: RgbaColor::rgb(0.5, 0.5, 0.5),
+            highlight: RgbaColor::rgb(1.0, 0.0, 0.0),
+            emphasis: RgbaColor::rgb(1.0, 1.0, 0.0),
+            button: RgbaColor::rgb(0.2, 0.2, 0.2),
+            button_hovered: RgbaColor::rgb(0.35, 0.35, 0.35),
+            typing: TypingColors::default(),
+        }
+    }
+}
+
+impl Theme {
+    /// the names of the themes shipped with the game
+    pub fn preset_names() -> impl Iterator<Item = &'static str> {
+        PRESETS.iter().map(|(name, _)| *name)
+    }
+
+    pub fn preset(name: &str) -> Option<Self> {
+        PRESETS
+            .iter()
+            .find(|(preset_name, _)| *preset_name == name)
+            .map(|(name, text)| {
+                let mut theme = Self::from_toml(text).expect("invalid preset theme");
+                theme.name = name.to_string();
+                theme
+            })
+    }
+
+    /// the directory the themes of the player are read from
+    pub fn directory() -> Option<PathBuf> {
+        paths::config_directory().map(|directory| directory.join("themes"))
+    }
+
+    pub fn from_toml(text: &str) -> io::Result<Self> {
+        toml::from_str(text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut theme = Self::from_toml(&fs::read_to_string(path)?)?;
+        theme.name = path.display().to_string();
+        Ok(theme)
+    }
+
+    /// the preset with that name, or else the theme <name>.toml of the theme directory, or else
+    /// the theme file at that path
+    pub fn find(name: &str) -> io::Result<Self> {
+        if let Some(theme) = Self::preset(name) {
+            return Ok(theme);
+        };
+        let in_directory =
+            Self::directory().map(|directory| directory.join(format!("{}.toml", name)));
+        let path = match in_directory {
+            Some(path) if path.is_file() => path,
+            _ => PathBuf::from(name),
+        };
+        let mut theme = Self::load(&path).map_err(|err| {
+            if err.kind() == io::ErrorKind::NotFound {
+                let presets: Vec<&str> = Self::preset_names().collect();
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!(
+                        "unknown theme, it must be {} or a theme file",
+                        presets.join(", ")
+                    ),
+                )
+            } else {
+                err
+            }
+        })?;
+        theme.name = name.to_string();
+        Ok(theme)
+    }
+
+    /// the label of a menu item, with a marker if it is highlighted and the emphasis style
+    /// require it
+    pub fn menu_label(&self, label: &str, highlighted: bool) -> String {
+        match (self.emphasis_style, highlighted) {
+            (EmphasisStyle::Marker, true) => format!("> {}", label),
+            (EmphasisStyle::Marker, false) => format!("  {}", label),
+            (EmphasisStyle::Color, _) => label.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{RgbaColor, Theme};
+
+    /// the relative luminance of a color, as defined by the WCAG
+    fn luminance(color: RgbaColor) -> f32 {
+        let linear = |component: f32| {
+            if component <= 0.03928 {
+                component / 12.92
+            } else {
+                ((component + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        let [red, green, blue, _] = color.0;
+        0.2126 * linear(red) + 0.7152 * linear(green) + 0.0722 * linear(blue)
+    }
+
+    fn contrast(first: RgbaColor, second: RgbaColor) -> f32 {
+        let (first, second) = (luminance(first), luminance(second));
+        (first.max(second) + 0.05) / (first.min(second) + 0.05)
+    }
+
+    #[test]
+    fn test_theme_presets() {
+        assert_eq!(Theme::preset("default"), Some(Theme::default()));
+        for name in Theme::preset_names().filter(|name| *name != "default") {
+            let theme = Theme::preset(name).unwrap();
+            assert_eq!(theme.name, name);
+            // the lyrics stay readable on the background
+            let minimum = if name == "high_contrast" { 7.0 } else { 4.5 };
+            let typing = &theme.typing;
+            for color in &[theme.text, typing.current, typing.untyped, typing.correct] {
+                assert!(contrast(*color, theme.background) >= minimum, "{}", name);
+            }
+            // the current line doesn't depend on the hue only
+            assert!(
+                contrast(typing.current, typing.non_current) >= 2.0,
+                "{}",
+                name
+            );
+            assert_ne!(typing.correct, typing.substituted);
+            assert_ne!(typing.correct, typing.missing);
+            assert_ne!(typing.substituted, typing.missing);
+        }
+        assert!(Theme::preset("unknown").is_none());
+    }
+
+    #[test]
+    fn test_theme_file() {
+        let theme =
+            Theme::from_toml("emphasis_style = \"marker\"\n[typing]\ncorrect = \"#00ff0080\"\n")
+                .unwrap();
+        assert_eq!(theme.text, Theme::default().text);
+        assert_eq!(
+            theme.typing.correct,
+            RgbaColor::rgba(0.0, 1.0, 0.0, 128.0 / 255.0)
+        );
+        assert_eq!(theme.menu_label("play", true), "> play");
+        assert_eq!(String::from(theme.typing.correct), "#00ff0080".to_string());
+        assert!(Theme::from_toml("[typing]\ncaret = \"yellow\"").is_err());
+        assert!(Theme::find("no such theme").is_err());
+    }
+}
@@ -0,0 +1,269 @@
+mod view;
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::{cursor, execute, terminal};
+use music_typer::{GameMode, Settings, SongSort};
+use music_typer::{LibraryEntry, PlayingMusic, Replay, ScoreDatabase, ScoreRecord, SongLibrary};
+use std::env;
+use std::io::{self, Stdout, Write};
+use std::panic;
+use std::path::Path;
+use std::process;
+use std::time::{Duration, Instant};
+use view::TerminalLayout;
+
+/// how long to wait for a key before drawing again, so that the statistics are updated
+const FRAME_DURATION: Duration = Duration::from_millis(100);
+
+/// what the player chose to do on a screen
+enum Action {
+    Continue,
+    Quit,
+}
+
+/// the songs of the file or directory given as argument, or of the library of the game
+fn load_songs() -> io::Result<Vec<LibraryEntry>> {
+    let library = match env::args_os().nth(1) {
+        Some(path) => {
+            let path = Path::new(&path);
+            if path.is_dir() {
+                SongLibrary::load(path)?
+            } else {
+                SongLibrary::new(vec![LibraryEntry::load(path)?])
+            }
+        }
+        None => match SongLibrary::default_directory() {
+            Some(directory) => SongLibrary::load(&directory)?,
+            None => SongLibrary::default(),
+        },
+    };
+    let order = library.sorted(SongSort::Title, |_| None);
+    Ok(order
+        .into_iter()
+        .map(|index| library.entries[index].clone())
+        .collect())
+}
+
+fn terminal_layout() -> crossterm::Result<TerminalLayout> {
+    let (width, height) = terminal::size()?;
+    Ok(TerminalLayout::new(width, height))
+}
+
+/// the key event read before the frame duration, if any. The terminal size is read again on
+/// resize.
+fn read_key(layout: &mut TerminalLayout) -> crossterm::Result<Option<KeyEvent>> {
+    if !event::poll(FRAME_DURATION)? {
+        return Ok(None);
+    };
+    match event::read()? {
+        Event::Key(key) => Ok(Some(key)),
+        Event::Resize(width, height) => {
+            *layout = TerminalLayout::new(width, height);
+            Ok(None)
+        }
+        _ => Ok(None),
+    }
+}
+
+fn is_interrupt(key: &KeyEvent) -> bool {
+    key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL)
+}
+
+/// let the player choose a song, return None to quit
+fn choose_song(
+    stdout: &mut Stdout,
+    entries: &[LibraryEntry],
+    selected: &mut usize,
+) -> crossterm::Result<Option<usize>> {
+    let labels: Vec<String> = entries.iter().map(view::song_label).collect();
+    let mut layout = terminal_layout()?;
+    loop {
+        view::draw_song_list(stdout, &layout, &labels, *selected)?;
+        let key = match read_key(&mut layout)? {
+            Some(key) => key,
+            None => continue,
+        };
+        if is_interrupt(&key) {
+            return Ok(None);
+        };
+        match key.code {
+            KeyCode::Esc => return Ok(None),
+            KeyCode::Up => *selected = (*selected + labels.len() - 1) % labels.len(),
+            KeyCode::Down => *selected = (*selected + 1) % labels.len(),
+            KeyCode::Enter => return Ok(Some(*selected)),
+            _ => (),
+        };
+    }
+}
+
+/// type a song, return it once it is finished, or None if the player left it
+fn play(
+    stdout: &mut Stdout,
+    entry: &LibraryEntry,
+    settings: &Settings,
+) -> crossterm::Result<(Action, Option<PlayingMusic>)> {
+    let title = view::song_label(entry);
+    let mut playing_music = entry.to_playing_music();
+    playing_music.set_precision(settings.precision);
+    let mut layout = terminal_layout()?;
+    let mut lines = layout.wrap(&playing_music);
+    let start = Instant::now();
+    while !playing_music.is_finished() {
+        view::draw_playing(stdout, &layout, &title, &playing_music, &lines)?;
+        let previous_layout = layout.clone();
+        let key = read_key(&mut layout)?;
+        if layout != previous_layout {
+            lines = layout.wrap(&playing_music);
+        };
+        playing_music.set_elapsed_time(start.elapsed());
+        let key = match key {
+            Some(key) => key,
+            None => continue,
+        };
+        if is_interrupt(&key) {
+            return Ok((Action::Quit, None));
+        };
+        let typed = match key.code {
+            KeyCode::Esc => return Ok((Action::Continue, None)),
+            KeyCode::End => {
+                playing_music.finish();
+                None
+            }
+            KeyCode::Backspace => Some('\u{8}'),
+            KeyCode::Enter => Some('\n'),
+            KeyCode::Char(chara)
+                if !key
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                Some(chara)
+            }
+            _ => None,
+        };
+        if let Some(chara) = typed {
+            playing_music.add_typed_char(chara);
+        };
+    }
+    Ok((Action::Continue, Some(playing_music)))
+}
+
+/// save the finished song in the score database of the game, and tell where it was saved.
+/// Practice runs aren't saved.
+fn save_score(
+    scores: Option<&ScoreDatabase>,
+    entry: &LibraryEntry,
+    playing_music: &PlayingMusic,
+    settings: &Settings,
+) -> String {
+    if settings.mode == GameMode::Practice {
+        return "practice runs aren't saved".to_string();
+    };
+    let (database, result) = match (scores, playing_music.result()) {
+        (Some(database), Some(result)) => (database, result),
+        _ => return "the score wasn't saved".to_string(),
+    };
+    let song_id = entry.title().unwrap_or_default().to_string();
+    let replay = Replay::from_playing_music(playing_music, song_id.clone());
+    let record = ScoreRecord::new(replay.settings.clone(), result);
+    match database.add_record(playing_music.song_hash(), &song_id, record, Some(&replay)) {
+        Ok(()) => format!("score saved in {}", database.path().display()),
+        Err(err) => format!("can't save the score in {:?}: {}", database.path(), err),
+    }
+}
+
+/// display the result of a finished song until the player leave it
+fn show_result(
+    stdout: &mut Stdout,
+    entry: &LibraryEntry,
+    playing_music: &PlayingMusic,
+    status: &str,
+) -> crossterm::Result<Action> {
+    let result = match playing_music.result() {
+        Some(result) => result,
+        None => return Ok(Action::Continue),
+    };
+    let title = view::song_label(entry);
+    let mut layout = terminal_layout()?;
+    loop {
+        view::draw_result(stdout, &layout, &title, &result, playing_music, status)?;
+        let key = match read_key(&mut layout)? {
+            Some(key) => key,
+            None => continue,
+        };
+        if is_interrupt(&key) {
+            return Ok(Action::Quit);
+        };
+        match key.code {
+            KeyCode::Enter => return Ok(Action::Continue),
+            KeyCode::Esc => return Ok(Action::Quit),
+            _ => (),
+        };
+    }
+}
+
+fn run(
+    stdout: &mut Stdout,
+    entries: &[LibraryEntry],
+    settings: &Settings,
+) -> crossterm::Result<()> {
+    let scores = ScoreDatabase::open_default();
+    let mut selected = 0;
+    loop {
+        let entry = match choose_song(stdout, entries, &mut selected)? {
+            Some(index) => &entries[index],
+            None => return Ok(()),
+        };
+        let playing_music = match play(stdout, entry, settings)? {
+            (Action::Quit, _) => return Ok(()),
+            (Action::Continue, None) => continue,
+            (Action::Continue, Some(playing_music)) => playing_music,
+        };
+        let status = save_score(scores.as_ref(), entry, &playing_music, settings);
+        if let Action::Quit = show_result(stdout, entry, &playing_music, &status)? {
+            return Ok(());
+        };
+    }
+}
+
+/// leave the alternate screen and the raw mode
+fn restore_terminal() -> crossterm::Result<()> {
+    execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen)
+        .and_then(|_| terminal::disable_raw_mode())
+}
+
+fn main() {
+    let settings = match Settings::default_path() {
+        Some(path) => Settings::load_or_default(&path).unwrap_or_else(|err| {
+            eprintln!("can't read the settings {:?}: {}", path, err);
+            process::exit(1);
+        }),
+        None => Settings::default(),
+    };
+    let entries = match load_songs() {
+        Ok(entries) => entries,
+        Err(err) => {
+            eprintln!("can't read the songs: {}", err);
+            process::exit(1);
+        }
+    };
+    if entries.is_empty() {
+        eprintln!("no song found, give a song file or directory as argument");
+        process::exit(1);
+    };
+    // restore the terminal before the panic message is printed, so that it can be read
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let _ = restore_terminal();
+        default_hook(info);
+    }));
+    let mut stdout = io::stdout();
+    let result = terminal::enable_raw_mode()
+        .and_then(|_| execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide))
+        .and_then(|_| run(&mut stdout, &entries, &settings));
+    // the terminal is restored even if the game failed
+    let restored = restore_terminal();
+    if let Err(err) = result.and(restored) {
+        eprintln!("terminal error: {}", err);
+        process::exit(1);
+    };
+}
@@ -0,0 +1,368 @@
+use crossterm::style::{Color, Print, ResetColor, SetForegroundColor};
+use crossterm::terminal::{Clear, ClearType};
+use crossterm::{cursor::MoveTo, queue};
+use music_typer::{AlignmentStatus, DiffRun, InputFieldView, LibraryEntry, PlayingMusic};
+use music_typer::{SessionResult, WrappedLines};
+use std::io::Write;
+use std::ops::Range;
+use unicode_width::UnicodeWidthStr;
+
+/// the colors of the terminal, following the default colors of the bevy game
+const CURRENT_COLOR: Color = Color::Red;
+const CARET_COLOR: Color = Color::Yellow;
+const TYPED_COLOR: Color = Color::Yellow;
+const PENDING_SPACE_COLOR: Color = Color::DarkYellow;
+const NON_CURRENT_COLOR: Color = Color::White;
+const SECTION_HEADING_COLOR: Color = Color::Grey;
+const HINT_COLOR: Color = Color::DarkGrey;
+
+fn status_color(status: Option<AlignmentStatus>) -> Color {
+    match status {
+        Some(AlignmentStatus::Correct) => Color::Green,
+        Some(AlignmentStatus::Substituted) => Color::DarkYellow,
+        Some(AlignmentStatus::Missing) => Color::Red,
+        Some(AlignmentStatus::Extra) => Color::Magenta,
+        Some(AlignmentStatus::Untyped) => Color::White,
+        None => Color::Grey,
+    }
+}
+
+/// where the lyrics are placed in the terminal. The lines are the same as in the bevy game: the
+/// typed rows are above the input line, the current row and the rows to type are below it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TerminalLayout {
+    pub width: u16,
+    pub height: u16,
+    /// the line of the input field
+    pub input_y: u16,
+    /// the number of typed rows displayed above the input line
+    pub top_displayed_line: usize,
+    /// the number of rows to type displayed below the current row
+    pub bottom_displayed_line: usize,
+}
+
+impl TerminalLayout {
+    /// the layout of a terminal of the given size. The first line is kept for the header and
+    /// the last one for the keys.
+    pub fn new(width: u16, height: u16) -> Self {
+        let input_y = height / 2;
+        Self {
+            width,
+            height,
+            input_y,
+            top_displayed_line: input_y.saturating_sub(2) as usize,
+            bottom_displayed_line: height.saturating_sub(input_y + 3) as usize,
+        }
+    }
+
+    /// the width the lines of the lyrics are wrapped at, in columns
+    pub fn max_line_width(&self) -> f32 {
+        self.width.saturating_sub(2).max(1) as f32
+    }
+
+    /// wrap the lines of the music in rows that fit in the terminal
+    pub fn wrap(&self, playing_music: &PlayingMusic) -> WrappedLines {
+        WrappedLines::new(playing_music.lines(), self.max_line_width(), |text| {
+            UnicodeWidthStr::width(text) as f32
+        })
+    }
+
+    /// the rows displayed above the input line, and the current row with the rows below it
+    pub fn displayed_rows(
+        &self,
+        current_row: usize,
+        row_len: usize,
+    ) -> (Range<usize>, Range<usize>) {
+        let current_row = current_row.min(row_len);
+        let end_row = (current_row + self.bottom_displayed_line + 1).min(row_len);
+        (
+            current_row.saturating_sub(self.top_displayed_line)..current_row,
+            current_row..end_row,
+        )
+    }
+}
+
+/// the runs of text of the given visual rows, with their color. As in the bevy game, the current
+/// line and the lines already typed are colored by the live alignment, with a caret at the
+/// aligned position, and the other rows are a single run.
+fn row_runs(
+    playing_music: &PlayingMusic,
+    lines: &WrappedLines,
+    rows: Range<usize>,
+) -> Vec<Vec<(String, Color)>> {
+    let mut result = vec![Vec::new(); rows.len()];
+    if rows.is_empty() {
+        return result;
+    };
+    let actual_line = playing_music.position_in_source_lines();
+    let first_line = lines.rows()[rows.start].line;
+    let end_line = lines.rows()[rows.end - 1].line + 1;
+    let live_diff = playing_music.live_diff(first_line, (actual_line + 1).max(first_line));
+    let mut caret_placed = false;
+    for line_count in first_line..end_line {
+        let line = &playing_music.lines()[line_count];
+        let line_rows: Vec<Vec<(String, Color)>> = if !playing_music.is_line_typed(line_count) {
+            lines
+                .split_text(line_count, line)
+                .into_iter()
+                .map(|text| vec![(text, SECTION_HEADING_COLOR)])
+                .collect()
+        } else if let Some(diff_runs) = live_diff.get(line_count - first_line) {
+            let row_diffs = lines.split_runs(line_count, diff_runs);
+            let row_diffs_len = row_diffs.len();
+            let mut line_rows = Vec::new();
+            for (row_count, diff_runs) in row_diffs.into_iter().enumerate() {
+                let mut runs = Vec::new();
+                for DiffRun { status, text } in diff_runs {
+                    if status == Some(AlignmentStatus::Untyped) && !caret_placed {
+                        runs.push(("|".to_string(), CARET_COLOR));
+                        caret_placed = true;
+                    };
+                    let color =
+                        if status == Some(AlignmentStatus::Untyped) && line_count == actual_line {
+                            CURRENT_COLOR
+                        } else {
+                            status_color(status)
+                        };
+                    runs.push((text, color));
+                }
+                if line_count == actual_line && row_count + 1 == row_diffs_len && !caret_placed {
+                    runs.push(("|".to_string(), CARET_COLOR));
+                    caret_placed = true;
+                };
+                line_rows.push(runs);
+            }
+            line_rows
+        } else {
+            lines
+                .split_text(line_count, line)
+                .into_iter()
+                .map(|text| vec![(text, NON_CURRENT_COLOR)])
+                .collect()
+        };
+        for (row_count, runs) in lines.rows_of_line(line_count).zip(line_rows) {
+            if rows.contains(&row_count) {
+                result[row_count - rows.start] = runs;
+            };
+        }
+    }
+    result
+}
+
+/// clear a line of the terminal, then write the runs from its first column
+fn draw_line(
+    stdout: &mut impl Write,
+    y: u16,
+    x: u16,
+    runs: &[(String, Color)],
+) -> crossterm::Result<()> {
+    queue!(
+        stdout,
+        MoveTo(0, y),
+        Clear(ClearType::CurrentLine),
+        MoveTo(x, y)
+    )?;
+    for (text, color) in runs {
+        queue!(stdout, SetForegroundColor(*color), Print(text))?;
+    }
+    queue!(stdout, ResetColor)
+}
+
+/// the title of a song, with its artist
+pub fn song_label(entry: &LibraryEntry) -> String {
+    match (entry.title(), entry.artist()) {
+        (Some(title), Some(artist)) => format!("{} - {}", title, artist),
+        (Some(title), None) => title.to_string(),
+        (None, Some(artist)) => artist.to_string(),
+        (None, None) => "untitled".to_string(),
+    }
+}
+
+/// draw the list of songs, the selected one being highlighted
+pub fn draw_song_list(
+    stdout: &mut impl Write,
+    layout: &TerminalLayout,
+    labels: &[String],
+    selected: usize,
+) -> crossterm::Result<()> {
+    draw_line(
+        stdout,
+        0,
+        1,
+        &[("Music Typer - choose a song".to_string(), NON_CURRENT_COLOR)],
+    )?;
+    // the list start on the third line and end before the last one
+    let displayed_count = layout.height.saturating_sub(3).max(1) as usize;
+    let first = selected.saturating_sub(displayed_count - 1);
+    for y in 1..layout.height.saturating_sub(1) {
+        let index = (first + y as usize).checked_sub(2);
+        let runs = match index.and_then(|index| labels.get(index).map(|label| (index, label))) {
+            Some((index, label)) if index == selected => {
+                vec![(format!("> {}", label), TYPED_COLOR)]
+            }
+            Some((_, label)) => vec![(format!("  {}", label), NON_CURRENT_COLOR)],
+            None => Vec::new(),
+        };
+        draw_line(stdout, y, 1, &runs)?;
+    }
+    draw_line(
+        stdout,
+        layout.height.saturating_sub(1),
+        1,
+        &[(
+            "Up/Down: choose  Enter: play  Escape: quit".to_string(),
+            HINT_COLOR,
+        )],
+    )?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// draw the header with the live statistics, the lyrics around the current row and the input
+/// line
+pub fn draw_playing(
+    stdout: &mut impl Write,
+    layout: &TerminalLayout,
+    title: &str,
+    playing_music: &PlayingMusic,
+    lines: &WrappedLines,
+) -> crossterm::Result<()> {
+    let stats = playing_music.stats();
+    let stats_text = format!(
+        "{:.0} wpm  {:.0}% accuracy  {:.0}% correctness",
+        stats.net_wpm,
+        stats.corrected_accuracy * 100.0,
+        playing_music.correctness() * 100.0
+    );
+    draw_line(stdout, 0, 1, &[(title.to_string(), SECTION_HEADING_COLOR)])?;
+    let stats_x = layout
+        .width
+        .saturating_sub(UnicodeWidthStr::width(stats_text.as_str()) as u16 + 1);
+    queue!(
+        stdout,
+        MoveTo(stats_x, 0),
+        SetForegroundColor(NON_CURRENT_COLOR),
+        Print(stats_text),
+        ResetColor
+    )?;
+
+    let current_row = lines.row_at(
+        playing_music.position_in_source_lines(),
+        playing_music.position_in_source_line(),
+    );
+    let (above, below) = layout.displayed_rows(current_row, lines.rows().len());
+    let runs = row_runs(playing_music, lines, above.start..below.end);
+    for y in 1..layout.height.saturating_sub(1) {
+        let row = if y < layout.input_y {
+            (current_row + y as usize).checked_sub(layout.input_y as usize)
+        } else if y > layout.input_y {
+            Some(current_row + (y - layout.input_y) as usize - 1)
+        } else {
+            continue;
+        };
+        let row_runs = row
+            .filter(|row| above.contains(row) || below.contains(row))
+            .map(|row| runs[row - above.start].as_slice())
+            .unwrap_or(&[]);
+        draw_line(stdout, y, 1, row_runs)?;
+    }
+
+    let view = InputFieldView::new(playing_music, layout.width.saturating_sub(4) as usize);
+    let mut input_runs = vec![("> ".to_string(), HINT_COLOR)];
+    for (text, mistake) in view.runs() {
+        let color = if mistake {
+            status_color(Some(AlignmentStatus::Substituted))
+        } else {
+            TYPED_COLOR
+        };
+        input_runs.push((text, color));
+    }
+    if view.pending_space {
+        input_runs.push(("_".to_string(), PENDING_SPACE_COLOR));
+    };
+    draw_line(stdout, layout.input_y, 0, &input_runs)?;
+    draw_line(
+        stdout,
+        layout.height.saturating_sub(1),
+        1,
+        &[(
+            "End: finish  Escape: leave the song".to_string(),
+            HINT_COLOR,
+        )],
+    )?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// draw the result of a finished song, and whether it was saved
+pub fn draw_result(
+    stdout: &mut impl Write,
+    layout: &TerminalLayout,
+    title: &str,
+    result: &SessionResult,
+    playing_music: &PlayingMusic,
+    status: &str,
+) -> crossterm::Result<()> {
+    let stats = playing_music.stats();
+    let lines = [
+        (title.to_string(), NON_CURRENT_COLOR),
+        (String::new(), NON_CURRENT_COLOR),
+        (
+            format!("correctness: {:.1}%", result.correctness * 100.0),
+            NON_CURRENT_COLOR,
+        ),
+        (
+            format!("speed: {:.0} wpm", stats.net_wpm),
+            NON_CURRENT_COLOR,
+        ),
+        (
+            format!("accuracy: {:.1}%", stats.corrected_accuracy * 100.0),
+            NON_CURRENT_COLOR,
+        ),
+        (
+            format!("time: {:.1} s", result.duration.as_secs_f64()),
+            NON_CURRENT_COLOR,
+        ),
+        (String::new(), NON_CURRENT_COLOR),
+        (status.to_string(), SECTION_HEADING_COLOR),
+    ];
+    for y in 1..layout.height.saturating_sub(1) {
+        let runs = match lines.get(y as usize - 1) {
+            Some(line) => vec![line.clone()],
+            None => Vec::new(),
+        };
+        draw_line(stdout, y, 1, &runs)?;
+    }
+    draw_line(
+        stdout,
+        layout.height.saturating_sub(1),
+        1,
+        &[(
+            "Enter: back to the songs  Escape: quit".to_string(),
+            HINT_COLOR,
+        )],
+    )?;
+    stdout.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::view::TerminalLayout;
+
+    #[test]
+    fn test_terminal_layout() {
+        let layout = TerminalLayout::new(80, 24);
+        assert_eq!(layout.input_y, 12);
+        assert_eq!(layout.top_displayed_line, 10);
+        assert_eq!(layout.bottom_displayed_line, 9);
+        // the current row and the rows below it go from input_y + 1 to the line before the last
+        assert_eq!(
+            layout.input_y as usize + 1 + layout.bottom_displayed_line,
+            layout.height as usize - 2
+        );
+        assert_eq!(layout.displayed_rows(0, 30), (0..0, 0..10));
+        assert_eq!(layout.displayed_rows(15, 30), (5..15, 15..25));
+        assert_eq!(layout.displayed_rows(28, 30), (18..28, 28..30));
+    }
+}
@@ -0,0 +1,278 @@
+use crate::{AlignmentStatus, DiffRun};
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// a part of a source line that is displayed on its own row
+#[derive(Debug, PartialEq, Clone)]
+pub struct VisualRow {
+    /// the index of the source line
+    pub line: usize,
+    /// the index of the first character of the row in the line, and of the character after its
+    /// last one
+    pub chars: Range<usize>,
+}
+
+/// split a line in ranges of characters that fit in max_width, as measured by the measure
+/// function. The line is cut after the spaces, and the words that are too long to fit on a row
+/// are cut between graphemes. Every row contains at least a grapheme, and an empty line is a
+/// single empty row.
+pub fn wrap_line(line: &str, max_width: f32, measure: impl Fn(&str) -> f32) -> Vec<Range<usize>> {
+    // the graphemes, with the index of the character they start at, and whether the line can be
+    // cut after them
+    let mut graphemes: Vec<(usize, &str, bool)> = Vec::new();
+    let mut char_index = 0;
+    let mut previous_is_space = false;
+    for grapheme in line.graphemes(true) {
+        let is_space = grapheme.chars().all(char::is_whitespace);
+        if previous_is_space && !is_space {
+            if let Some(last) = graphemes.last_mut() {
+                last.2 = true;
+            };
+        };
+        graphemes.push((char_index, grapheme, false));
+        char_index += grapheme.chars().count();
+        previous_is_space = is_space;
+    }
+    let line_len = char_index;
+    let fit = |start: usize, end: usize| {
+        let text: String = graphemes[start..end]
+            .iter()
+            .map(|(_, grapheme, _)| *grapheme)
+            .collect();
+        measure(text.trim_end()) <= max_width
+    };
+
+    let mut rows = Vec::new();
+    // the index of the first grapheme of the row
+    let mut row_start = 0;
+    while row_start < graphemes.len() {
+        let mut row_end = row_start + 1;
+        // the end of the row if it is cut after a space
+        let mut last_break = None;
+        while row_end < graphemes.len() && fit(row_start, row_end + 1) {
+            if graphemes[row_end - 1].2 {
+                last_break = Some(row_end);
+            };
+            row_end += 1;
+        }
+        if row_end < graphemes.len() && !graphemes[row_end - 1].2 {
+            // don't cut a word if it can be placed on the next row
+            if let Some(last_break) = last_break {
+                row_end = last_break;
+            };
+        };
+        rows.push(graphemes[row_start].0..graphemes.get(row_end).map_or(line_len, |g| g.0));
+        row_start = row_end;
+    }
+    if rows.is_empty() {
+        rows.push(0..0);
+    };
+    rows
+}
+
+/// the lines of a song, wrapped in visual rows
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct WrappedLines {
+    rows: Vec<VisualRow>,
+    /// the index of the first row of every line, and the number of rows at the end
+    first_rows: Vec<usize>,
+}
+
+impl WrappedLines {
+    pub fn new(lines: &[String], max_width: f32, measure: impl Fn(&str) -> f32) -> Self {
+        let mut rows = Vec::new();
+        let mut first_rows = Vec::new();
+        for (line_count, line) in lines.iter().enumerate() {
+            first_rows.push(rows.len());
+            for chars in wrap_line(line, max_width, &measure) {
+                rows.push(VisualRow {
+                    line: line_count,
+                    chars,
+                });
+            }
+        }
+        first_rows.push(rows.len());
+        Self { rows, first_rows }
+    }
+
+    pub fn rows(&self) -> &[VisualRow] {
+        &self.rows
+    }
+
+    /// the index of the rows the given line is displayed on
+    pub fn rows_of_line(&self, line: usize) -> Range<usize> {
+        match (self.first_rows.get(line), self.first_rows.get(line + 1)) {
+            (Some(first_row), Some(end_row)) => *first_row..*end_row,
+            _ => self.rows.len()..self.rows.len(),
+        }
+    }
+
+    /// the row that display the given character of a line. A position at the end of the line
+    /// is on its last row.
+    pub fn row_at(&self, line: usize, chara: usize) -> usize {
+        let rows = self.rows_of_line(line);
+        if rows.is_empty() {
+            return self.rows.len().saturating_sub(1);
+        };
+        rows.clone()
+            .rev()
+            .find(|row| self.rows[*row].chars.start <= chara)
+            .unwrap_or(rows.start)
+    }
+
+    /// split the runs of a line, as given by PlayingMusic::live_diff, in the runs of each of its
+    /// rows. The extra typed characters are kept on the row of the source character before them.
+    pub fn split_runs(&self, line: usize, runs: &[DiffRun]) -> Vec<Vec<DiffRun>> {
+        let rows = &self.rows[self.rows_of_line(line)];
+        let mut result: Vec<Vec<DiffRun>> = vec![Vec::new(); rows.len().max(1)];
+        let mut row = 0;
+        let mut char_index = 0;
+        for run in runs {
+            if run.status == Some(AlignmentStatus::Extra) {
+                push_run(&mut result[row], run.status, &run.text);
+                continue;
+            };
+            for chara in run.text.chars() {
+                while row + 1 < rows.len() && char_index >= rows[row].chars.end {
+                    row += 1;
+                }
+                push_run(&mut result[row], run.status, &chara.to_string());
+                char_index += 1;
+            }
+        }
+        result
+    }
+
+    /// split a line of plain text in the text of each of its rows
+    pub fn split_text(&self, line: usize, text: &str) -> Vec<String> {
+        let rows = &self.rows[self.rows_of_line(line)];
+        rows.iter()
+            .map(|row| {
+                text.chars()
+                    .skip(row.chars.start)
+                    .take(row.chars.end - row.chars.start)
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// split a text aligned above a line, like its chords, in the text above each of its rows.
+    /// A word is kept whole on the row it start on.
+    pub fn split_aligned_text(&self, line: usize, text: &str) -> Vec<String> {
+        let chars: Vec<char> = text.chars().collect();
+        let rows = &self.rows[self.rows_of_line(line)];
+        rows.iter()
+            .enumerate()
+            .map(|(row_count, row)| {
+                let mut start = row.chars.start.min(chars.len());
+                // the end of a word started on the previous row
+                while row_count > 0
+                    && start > 0
+                    && start < chars.len()
+                    && !chars[start - 1].is_whitespace()
+                    && !chars[start].is_whitespace()
+                {
+                    start += 1;
+                }
+                let mut end = if row_count + 1 == rows.len() {
+                    chars.len()
+                } else {
+                    row.chars.end.min(chars.len()).max(start)
+                };
+                while end > start && end < chars.len() && !chars[end].is_whitespace() {
+                    end += 1;
+                }
+                let mut result: String = (row.chars.start..start).map(|_| ' ').collect();
+                result.extend(&chars[start..end]);
+                result.trim_end().to_string()
+            })
+            .collect()
+    }
+}
+
+/// add text to the last run if it has the same status, or in a new run
+fn push_run(runs: &mut Vec<DiffRun>, status: Option<AlignmentStatus>, text: &str) {
+    match runs.last_mut() {
+        Some(run) if run.status == status => run.text.push_str(text),
+        _ => runs.push(DiffRun {
+            status,
+            text: text.to_string(),
+        }),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{wrap_line, AlignmentStatus, DiffRun, WrappedLines};
+
+    fn measure(text: &str) -> f32 {
+        text.chars().count() as f32
+    }
+
+    #[test]
+    fn test_wrap_line() {
+        assert_eq!(wrap_line("", 10.0, measure), vec![0..0]);
+        assert_eq!(wrap_line("hello world", 20.0, measure), vec![0..11]);
+        assert_eq!(wrap_line("hello world", 8.0, measure), vec![0..6, 6..11]);
+        // the trailing space isn't measured
+        assert_eq!(wrap_line("hello world", 5.0, measure), vec![0..6, 6..11]);
+        assert_eq!(
+            wrap_line("a verylongword b", 5.0, measure),
+            vec![0..2, 2..7, 7..12, 12..16]
+        );
+        // graphemes aren't split
+        assert_eq!(
+            wrap_line("e\u{301}e\u{301}e\u{301}", 4.0, measure),
+            vec![0..4, 4..6]
+        );
+    }
+
+    #[test]
+    fn test_wrapped_lines() {
+        let lines = vec![
+            "first line".to_string(),
+            "the second line is long".to_string(),
+        ];
+        let wrapped = WrappedLines::new(&lines, 12.0, measure);
+        assert_eq!(wrapped.rows().len(), 3);
+        assert_eq!(wrapped.rows_of_line(1), 1..3);
+        assert_eq!(wrapped.rows()[2].chars, 11..23);
+        assert_eq!(wrapped.row_at(0, 4), 0);
+        assert_eq!(wrapped.row_at(1, 10), 1);
+        assert_eq!(wrapped.row_at(1, 11), 2);
+        assert_eq!(wrapped.row_at(1, 23), 2);
+        assert_eq!(
+            wrapped.split_text(1, &lines[1]),
+            vec!["the second ", "line is long"]
+        );
+        assert_eq!(
+            wrapped.split_aligned_text(1, "C     Amaj7 G"),
+            vec!["C     Amaj7", " G"]
+        );
+
+        let run = |status, text: &str| DiffRun {
+            status: Some(status),
+            text: text.to_string(),
+        };
+        let runs = vec![
+            run(AlignmentStatus::Correct, "the second"),
+            run(AlignmentStatus::Extra, "xx"),
+            run(AlignmentStatus::Correct, " l"),
+            run(AlignmentStatus::Untyped, "ine is long"),
+        ];
+        assert_eq!(
+            wrapped.split_runs(1, &runs),
+            vec![
+                vec![
+                    run(AlignmentStatus::Correct, "the second"),
+                    run(AlignmentStatus::Extra, "xx"),
+                    run(AlignmentStatus::Correct, " "),
+                ],
+                vec![
+                    run(AlignmentStatus::Correct, "l"),
+                    run(AlignmentStatus::Untyped, "ine is long"),
+                ],
+            ]
+        );
+    }
+}